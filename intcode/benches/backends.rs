@@ -0,0 +1,27 @@
+//! Compares the interpreter with the compiled backend on day 9 part 2.
+//!
+//! Run with `cargo bench -p intcode --bench backends`.
+
+use intcode::{Backend, VM};
+use std::time::Instant;
+
+const ROUNDS: u32 = 20;
+
+fn main() {
+    let code = intcode::parse_program(include_str!("../../day9/input.txt")).unwrap();
+
+    for backend in &[Backend::Interpreter, Backend::Compiled] {
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            let mut vm = VM::new(code.clone());
+            vm.set_backend(*backend);
+            assert_eq!(vm.run_to_completion(vec![2]), Ok(vec![66113]));
+        }
+
+        println!(
+            "{:<12} {:>10.2?}",
+            format!("{:?}", backend),
+            start.elapsed() / ROUNDS
+        );
+    }
+}
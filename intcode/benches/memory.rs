@@ -0,0 +1,124 @@
+//! Compares `Memory` with the layout it replaced, a vector for the program
+//! and a hash map for everything past it.
+//!
+//! Run with `cargo bench -p intcode --bench memory`.
+
+use intcode::rng::Rng;
+use intcode::{Memory, VM};
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ROUNDS: usize = 200;
+
+struct HashMapMemory {
+    code: Vec<i64>,
+    extra_memory: HashMap<usize, i64>,
+}
+
+impl HashMapMemory {
+    fn get(&self, idx: usize) -> i64 {
+        if idx < self.code.len() {
+            self.code[idx]
+        } else {
+            *self.extra_memory.get(&idx).unwrap_or(&0)
+        }
+    }
+
+    fn set(&mut self, idx: usize, val: i64) {
+        if idx < self.code.len() {
+            self.code[idx] = val;
+        } else {
+            self.extra_memory.insert(idx, val);
+        }
+    }
+}
+
+trait Access {
+    fn get(&self, idx: usize) -> i64;
+    fn set(&mut self, idx: usize, val: i64);
+}
+
+impl Access for HashMapMemory {
+    fn get(&self, idx: usize) -> i64 {
+        HashMapMemory::get(self, idx)
+    }
+
+    fn set(&mut self, idx: usize, val: i64) {
+        HashMapMemory::set(self, idx, val)
+    }
+}
+
+impl Access for Memory {
+    fn get(&self, idx: usize) -> i64 {
+        Memory::get(self, idx)
+    }
+
+    fn set(&mut self, idx: usize, val: i64) {
+        Memory::set(self, idx, val)
+    }
+}
+
+/// Adds each word in `addresses` to the next, like a program walking a table.
+fn workload<M: Access>(memory: &mut M, addresses: &[usize]) {
+    for pair in addresses.windows(2) {
+        let val = memory.get(pair[0]).wrapping_add(memory.get(pair[1]));
+        memory.set(pair[1], val);
+    }
+}
+
+fn time<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    start.elapsed() / ROUNDS as u32
+}
+
+fn compare(name: &str, code: &[i64], addresses: &[usize]) {
+    let old = time(|| {
+        let mut memory = HashMapMemory {
+            code: code.to_vec(),
+            extra_memory: HashMap::new(),
+        };
+        workload(&mut memory, addresses);
+        black_box(memory.get(addresses[0]));
+    });
+    let new = time(|| {
+        let mut memory = Memory::new(code.to_vec());
+        workload(&mut memory, addresses);
+        black_box(memory.get(addresses[0]));
+    });
+
+    println!(
+        "{:<28} hash map {:>10.2?}   paged {:>10.2?}",
+        name, old, new
+    );
+}
+
+fn main() {
+    let code = intcode::parse_program(include_str!("../../day9/input.txt")).unwrap();
+    let len = code.len();
+
+    let mut rng = Rng::new(0);
+    let mut random = move |max: usize| rng.range(0, max as i64 - 1) as usize;
+
+    let program = (0..100_000).map(|_| random(len)).collect::<Vec<_>>();
+    let near = (0..100_000).map(|_| len + random(1000)).collect::<Vec<_>>();
+    let sparse = (0..100_000)
+        .map(|_| 1 << 40 | random(1 << 30))
+        .collect::<Vec<_>>();
+
+    compare("inside the program", &code, &program);
+    compare("just past the program", &code, &near);
+    compare("sparse high addresses", &code, &sparse);
+
+    let start = Instant::now();
+    let output = VM::new(code).run_to_completion(vec![2]).unwrap();
+    println!(
+        "{:<28} {:?} ({:?})",
+        "day 9 part 2",
+        start.elapsed(),
+        output
+    );
+}
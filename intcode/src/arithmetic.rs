@@ -0,0 +1,62 @@
+/// What happens when an addition or multiplication doesn't fit in the VM's
+/// word type, whether it is done by an instruction or in working out a
+/// relative address. `BigInt` words never overflow, so for them the modes
+/// are all the same.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Arithmetic {
+    /// Wraps around, as in two's complement.
+    #[default]
+    Wrapping,
+    /// Fails with `IntcodeError::Overflow`.
+    Checked,
+    /// Stops at the smallest or largest value of the word type.
+    Saturating,
+}
+
+impl Arithmetic {
+    /// Adds `i64` words `a` and `b`, returning `None` on overflow in
+    /// `Checked` mode.
+    pub fn add(self, a: i64, b: i64) -> Option<i64> {
+        match self {
+            Arithmetic::Wrapping => Some(a.wrapping_add(b)),
+            Arithmetic::Checked => a.checked_add(b),
+            Arithmetic::Saturating => Some(a.saturating_add(b)),
+        }
+    }
+
+    /// Multiplies `i64` words `a` and `b`, returning `None` on overflow in
+    /// `Checked` mode.
+    pub fn mul(self, a: i64, b: i64) -> Option<i64> {
+        match self {
+            Arithmetic::Wrapping => Some(a.wrapping_mul(b)),
+            Arithmetic::Checked => a.checked_mul(b),
+            Arithmetic::Saturating => Some(a.saturating_mul(b)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_overflow() {
+        use Arithmetic::*;
+
+        assert_eq!(Wrapping.add(i64::MAX, 1), Some(i64::MIN));
+        assert_eq!(Checked.add(i64::MAX, 1), None);
+        assert_eq!(Saturating.add(i64::MAX, 1), Some(i64::MAX));
+
+        assert_eq!(Wrapping.mul(i64::MIN, -1), Some(i64::MIN));
+        assert_eq!(Checked.mul(i64::MIN, -1), None);
+        assert_eq!(Saturating.mul(i64::MIN, 2), Some(i64::MIN));
+
+        for mode in &[Wrapping, Checked, Saturating] {
+            assert_eq!(mode.add(-5, 3), Some(-2));
+            assert_eq!(
+                mode.mul(34_915_192, 34_915_192),
+                Some(1_219_070_632_396_864)
+            );
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//! Running programs that talk in ASCII: they print characters and read lines
+//! of text.
+//!
+//! Outputs below 128 are characters. Anything else is a numeric result, which
+//! such programs usually produce once at the very end.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+
+use crate::error::IntcodeError;
+use crate::io::IntcodeIo;
+use crate::vm::{ExecutionStatus, VM};
+
+fn is_ascii(value: i64) -> bool {
+    (0..128).contains(&value)
+}
+
+/// What a program printed before asking for input or halting.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AsciiOutput {
+    pub text: String,
+    /// Outputs that weren't characters, in the order they were printed.
+    pub values: Vec<i64>,
+    pub halted: bool,
+}
+
+/// Drives a VM a line at a time from Rust code.
+#[derive(Debug)]
+pub struct AsciiVm {
+    vm: VM,
+    input: VecDeque<i64>,
+}
+
+impl AsciiVm {
+    pub fn new(vm: VM) -> Self {
+        Self {
+            vm,
+            input: VecDeque::new(),
+        }
+    }
+
+    pub fn vm(&mut self) -> &mut VM {
+        &mut self.vm
+    }
+
+    pub fn into_inner(self) -> VM {
+        self.vm
+    }
+
+    /// Queues `line` followed by a newline for the program to read.
+    pub fn send_line(&mut self, line: &str) {
+        self.input.extend(line.chars().map(|c| c as i64));
+        self.input.push_back(i64::from(b'\n'));
+    }
+
+    /// Runs until the program wants input that hasn't been sent yet, or
+    /// halts.
+    pub fn read_until_prompt(&mut self) -> Result<AsciiOutput, IntcodeError> {
+        let mut output = AsciiOutput::default();
+        let mut status = self.vm.resume(None)?;
+
+        loop {
+            status = match status {
+                ExecutionStatus::Output(n) if is_ascii(n) => {
+                    output.text.push(n as u8 as char);
+                    self.vm.resume(None)?
+                }
+                ExecutionStatus::Output(n) => {
+                    output.values.push(n);
+                    self.vm.resume(None)?
+                }
+                ExecutionStatus::AwaitingInput => match self.input.pop_front() {
+                    Some(c) => self.vm.resume(Some(c))?,
+                    None => return Ok(output),
+                },
+                ExecutionStatus::Complete => {
+                    output.halted = true;
+                    return Ok(output);
+                }
+                ExecutionStatus::OutOfFuel => {
+                    return Err(IntcodeError::OutOfFuel {
+                        fault: self.vm.fault(),
+                    })
+                }
+                ExecutionStatus::Watchpoint(_) => self.vm.resume(None)?,
+            };
+        }
+    }
+}
+
+/// An `IntcodeIo` that reads lines from `input` and writes text to `output`,
+/// with numeric results on lines of their own.
+#[derive(Debug)]
+pub struct AsciiIo<R, W> {
+    input: R,
+    output: W,
+    line: VecDeque<i64>,
+    at_line_start: bool,
+}
+
+impl<R: BufRead, W: Write> AsciiIo<R, W> {
+    pub fn new(input: R, output: W) -> Self {
+        Self {
+            input,
+            output,
+            line: VecDeque::new(),
+            at_line_start: true,
+        }
+    }
+
+    pub fn into_inner(self) -> (R, W) {
+        (self.input, self.output)
+    }
+}
+
+impl AsciiIo<io::StdinLock<'static>, io::Stdout> {
+    pub fn stdio() -> Self {
+        Self::new(io::stdin().lock(), io::stdout())
+    }
+}
+
+impl<R: BufRead, W: Write> IntcodeIo for AsciiIo<R, W> {
+    fn input(&mut self) -> io::Result<Option<i64>> {
+        if self.line.is_empty() {
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+
+            let line = line.trim_end_matches(&['\r', '\n'][..]);
+            self.line.extend(line.chars().map(|c| c as i64));
+            self.line.push_back(i64::from(b'\n'));
+        }
+
+        Ok(self.line.pop_front())
+    }
+
+    fn output(&mut self, value: i64) -> io::Result<()> {
+        if is_ascii(value) {
+            self.at_line_start = value == i64::from(b'\n');
+            write!(self.output, "{}", value as u8 as char)
+        } else {
+            if !self.at_line_start {
+                writeln!(self.output)?;
+            }
+            self.at_line_start = true;
+            writeln!(self.output, "{}", value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asm::assemble;
+
+    /// Asks for a line and prints 1000 plus its length.
+    fn count() -> VM {
+        VM::new(
+            assemble(
+                "
+                        out #63
+                loop:   in [c]
+                        eq [c], #10, [t]
+                        jt [t], #done
+                        add [n], #1, [n]
+                        jt #1, #loop
+                done:   add [n], #1000, [n]
+                        out [n]
+                        out #33
+                        hlt
+                c:      data 0
+                t:      data 0
+                n:      data 0
+                ",
+            )
+            .unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_ascii_vm() {
+        let mut vm = AsciiVm::new(count());
+
+        assert_eq!(
+            vm.read_until_prompt(),
+            Ok(AsciiOutput {
+                text: "?".into(),
+                values: vec![],
+                halted: false,
+            })
+        );
+
+        vm.send_line("hello");
+        assert_eq!(
+            vm.read_until_prompt(),
+            Ok(AsciiOutput {
+                text: "!".into(),
+                values: vec![1005],
+                halted: true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_ascii_io() {
+        let mut io = AsciiIo::new(&b"abc\r\n"[..], Vec::new());
+        count().run_with(&mut io).unwrap();
+        assert_eq!(io.into_inner().1, b"?\n1003\n!");
+
+        let mut io = AsciiIo::new(&b""[..], Vec::new());
+        assert!(matches!(
+            count().run_with(&mut io),
+            Err(IntcodeError::InputExhausted { .. })
+        ));
+    }
+}
@@ -0,0 +1,352 @@
+//! An assembler for the syntax printed by the disassembler.
+//!
+//! Each line holds at most one instruction or `data` directive, optionally
+//! preceded by `label:` definitions and the `0042:` address annotations of a
+//! disassembly listing. Operands are written `#5` (immediate), `[100]`
+//! (positional) or `[r+3]` (relative), and anywhere a number is expected a
+//! label or a sum like `loop+2` may be used instead. Comments start with `;`.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+
+use crate::opcode::{OpCode, ParamMode};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+/// A sum of numbers and labels, resolved once every label is known.
+#[derive(Debug)]
+struct Expr(Vec<(bool, Term)>);
+
+#[derive(Debug)]
+enum Term {
+    Number(i64),
+    Label(String),
+}
+
+#[derive(Debug)]
+enum Statement {
+    Instruction(OpCode, Vec<(ParamMode, Expr)>),
+    Data(Vec<Expr>),
+}
+
+impl Statement {
+    fn len(&self) -> usize {
+        match self {
+            Statement::Instruction(opcode, _) => opcode.effect(),
+            Statement::Data(words) => words.len(),
+        }
+    }
+}
+
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' || c == '.' => {}
+        _ => return false,
+    }
+
+    s != "r" && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+fn parse_expr(s: &str) -> Result<Expr, String> {
+    let s = s.trim();
+    let mut terms = Vec::new();
+    let mut rest = s;
+
+    while !rest.is_empty() {
+        let negative = rest.starts_with('-');
+        if negative || rest.starts_with('+') {
+            rest = rest[1..].trim_start();
+        } else if !terms.is_empty() {
+            return Err(format!("Invalid expression `{}`", s));
+        }
+
+        let end = rest
+            .char_indices()
+            .skip(1)
+            .find(|(_, c)| *c == '+' || *c == '-')
+            .map_or(rest.len(), |(i, _)| i);
+        let term = rest[..end].trim();
+
+        if is_label(term) {
+            terms.push((negative, Term::Label(term.to_string())));
+        } else {
+            // The sign is parsed along with the digits so that i64::MIN fits
+            let number = if negative {
+                format!("-{}", term).parse()
+            } else {
+                term.parse()
+            };
+
+            terms.push((
+                false,
+                Term::Number(number.map_err(|_| format!("Invalid number `{}`", term))?),
+            ));
+        }
+        rest = rest[end..].trim_start();
+    }
+
+    if terms.is_empty() {
+        return Err("Expected a value".into());
+    }
+
+    Ok(Expr(terms))
+}
+
+fn parse_operand(s: &str) -> Result<(ParamMode, Expr), String> {
+    let s = s.trim();
+
+    if let Some(value) = s.strip_prefix('#') {
+        return Ok((ParamMode::Immediate, parse_expr(value)?));
+    }
+
+    let inner = s
+        .strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .ok_or_else(|| format!("Invalid operand `{}`", s))?
+        .trim();
+
+    match inner.strip_prefix('r') {
+        Some("") => Ok((ParamMode::Relative, Expr(vec![(false, Term::Number(0))]))),
+        Some(offset) if offset.trim_start().starts_with(&['+', '-'][..]) => {
+            Ok((ParamMode::Relative, parse_expr(offset)?))
+        }
+        _ => Ok((ParamMode::Positional, parse_expr(inner)?)),
+    }
+}
+
+fn parse_statement(s: &str) -> Result<Statement, String> {
+    let (name, rest) = match s.find(char::is_whitespace) {
+        Some(i) => (&s[..i], s[i..].trim()),
+        None => (s, ""),
+    };
+    let args = if rest.is_empty() {
+        Vec::new()
+    } else {
+        rest.split(',').collect()
+    };
+
+    if name == "data" {
+        if args.is_empty() {
+            return Err("Expected at least one value after `data`".into());
+        }
+        return Ok(Statement::Data(
+            args.into_iter().map(parse_expr).collect::<Result<_, _>>()?,
+        ));
+    }
+
+    let opcode =
+        OpCode::from_mnemonic(name).ok_or_else(|| format!("Unknown mnemonic `{}`", name))?;
+
+    if args.len() != opcode.param_count() {
+        return Err(format!(
+            "`{}` takes {} operand(s), found {}",
+            name,
+            opcode.param_count(),
+            args.len()
+        ));
+    }
+
+    let operands = args
+        .into_iter()
+        .map(parse_operand)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if let Some(dest) = opcode.dest_param() {
+        if operands[dest].0 == ParamMode::Immediate {
+            return Err(format!(
+                "Operand {} of `{}` cannot be immediate",
+                dest + 1,
+                name
+            ));
+        }
+    }
+
+    Ok(Statement::Instruction(opcode, operands))
+}
+
+/// Assembles `source` into a program.
+pub fn assemble(source: &str) -> Result<Vec<i64>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
+    let mut address = 0;
+
+    for (i, line) in source.lines().enumerate() {
+        let line_number = i + 1;
+        let error = |message| AsmError {
+            line: line_number,
+            message,
+        };
+
+        let mut line = line.split(';').next().unwrap().trim();
+
+        while let Some(colon) = line.find(':') {
+            let prefix = line[..colon].trim();
+
+            if is_label(prefix) {
+                if labels.insert(prefix.to_string(), address).is_some() {
+                    return Err(error(format!("Duplicate label `{}`", prefix)));
+                }
+            } else if let Ok(annotation) = prefix.parse::<usize>() {
+                if annotation != address {
+                    return Err(error(format!(
+                        "Address {} does not match actual address {}",
+                        annotation, address
+                    )));
+                }
+            } else {
+                return Err(error(format!("Invalid label `{}`", prefix)));
+            }
+
+            line = line[colon + 1..].trim();
+        }
+
+        if line.is_empty() {
+            continue;
+        }
+
+        let statement = parse_statement(line).map_err(error)?;
+        address += statement.len();
+        statements.push((line_number, statement));
+    }
+
+    let mut code = Vec::with_capacity(address);
+
+    for (line_number, statement) in statements {
+        let resolve = |Expr(terms): &Expr| {
+            terms.iter().try_fold(0i64, |acc, (negative, term)| {
+                let value = match term {
+                    Term::Number(n) => *n,
+                    Term::Label(label) => *labels.get(label).ok_or_else(|| AsmError {
+                        line: line_number,
+                        message: format!("Undefined label `{}`", label),
+                    })? as i64,
+                };
+
+                if *negative {
+                    acc.checked_sub(value)
+                } else {
+                    acc.checked_add(value)
+                }
+                .ok_or_else(|| AsmError {
+                    line: line_number,
+                    message: "Value out of range".into(),
+                })
+            })
+        };
+
+        match statement {
+            Statement::Instruction(opcode, operands) => {
+                let modes = operands
+                    .iter()
+                    .rev()
+                    .fold(0, |acc, (mode, _)| acc * 10 + *mode as i64);
+                code.push(modes * 100 + opcode as i64);
+
+                for (_, value) in &operands {
+                    code.push(resolve(value)?);
+                }
+            }
+            Statement::Data(words) => {
+                for word in &words {
+                    code.push(resolve(word)?);
+                }
+            }
+        }
+    }
+
+    Ok(code)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::disasm::disassemble;
+    use crate::vm::VM;
+
+    fn round_trip(code: &[i64]) {
+        let listing = disassemble(code)
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        assert_eq!(assemble(&listing).as_deref(), Ok(code));
+    }
+
+    #[test]
+    fn test_labels() {
+        let source = "
+            ; counts down from 3
+                    add #3, #0, [counter]
+            loop:   out [counter]
+                    add [counter], #-1, [counter]
+                    jt [counter], #loop
+                    hlt
+            counter: data 0
+        ";
+        let code = assemble(source).unwrap();
+
+        assert_eq!(
+            code,
+            vec![1101, 3, 0, 14, 4, 14, 1001, 14, -1, 14, 1005, 14, 4, 99, 0]
+        );
+        assert_eq!(VM::new(code).run_to_completion(vec![]), Ok(vec![3, 2, 1]));
+    }
+
+    #[test]
+    fn test_operands() {
+        assert_eq!(
+            assemble("add [r+3], #-5, [r]\narb #end-1\nend: data end+1, -end"),
+            Ok(vec![21201, 3, -5, 0, 109, 5, 7, -6])
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(&[
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ]);
+        round_trip(&[1105, 1, 7, 0, -5, 12345, 11101, 99, 1, 2]);
+        round_trip(&[104, i64::MIN, 104, i64::MAX, 99]);
+        round_trip(&crate::parse_program(include_str!("../../day9/input.txt")).unwrap());
+        round_trip(&crate::parse_program(include_str!("../../day13/input.txt")).unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |source, line, message: &str| {
+            assert_eq!(
+                assemble(source),
+                Err(AsmError {
+                    line,
+                    message: message.to_string()
+                })
+            );
+        };
+
+        error("hlt\nnop", 2, "Unknown mnemonic `nop`");
+        error("add #1, #2", 1, "`add` takes 3 operand(s), found 2");
+        error("in #1", 1, "Operand 1 of `in` cannot be immediate");
+        error("out 5", 1, "Invalid operand `5`");
+        error("out #five", 1, "Undefined label `five`");
+        error("a: hlt\n\na: hlt", 3, "Duplicate label `a`");
+        error("0001: hlt", 1, "Address 1 does not match actual address 0");
+        error("out #1x", 1, "Invalid number `1x`");
+        error("data", 1, "Expected at least one value after `data`");
+    }
+}
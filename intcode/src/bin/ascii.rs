@@ -0,0 +1,14 @@
+use intcode::{AsciiIo, VM};
+use std::env;
+use std::fs;
+
+/// Runs a program that talks in ASCII, reading its input a line at a time
+/// from stdin.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = env::args().nth(1).ok_or("Usage: ascii <program>")?;
+    let code = intcode::parse_program(&fs::read_to_string(path)?)?;
+
+    VM::new(code).run_with(&mut AsciiIo::stdio())?;
+
+    Ok(())
+}
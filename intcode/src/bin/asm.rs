@@ -0,0 +1,18 @@
+use intcode::asm::assemble;
+use std::env;
+use std::fs;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = env::args().nth(1).ok_or("Usage: asm <source>")?;
+    let code = assemble(&fs::read_to_string(path)?)?;
+
+    println!(
+        "{}",
+        code.iter()
+            .map(i64::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+
+    Ok(())
+}
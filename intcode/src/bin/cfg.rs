@@ -0,0 +1,19 @@
+use intcode::cfg::Cfg;
+use std::env;
+use std::fs;
+
+/// Prints the control-flow graph of a program in Graphviz's DOT language, for
+/// example to render with `cfg program.txt | dot -Tsvg > cfg.svg`. The
+/// addresses of indirect jumps are listed on stderr.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = env::args().nth(1).ok_or("Usage: cfg <program>")?;
+    let code = intcode::parse_program(&fs::read_to_string(path)?)?;
+    let cfg = Cfg::new(&code);
+
+    print!("{}", cfg.to_dot());
+    for address in cfg.indirect_jumps() {
+        eprintln!("indirect jump at {:04}", address);
+    }
+
+    Ok(())
+}
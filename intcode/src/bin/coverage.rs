@@ -0,0 +1,34 @@
+use intcode::{FnIo, VM};
+use std::env;
+use std::fs;
+
+/// Runs a program with the inputs given on the command line and prints which
+/// of its instructions were executed, as a listing or, with `--html`, as a
+/// web page. The listing is of the program as it was when it halted, so it
+/// shows instructions it wrote itself, and is printed even if the program
+/// fails. The program's output goes to stderr.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let usage = "Usage: coverage [--html] <program> [input...]";
+    let mut args = env::args().skip(1).peekable();
+    let html = args.next_if(|arg| arg == "--html").is_some();
+    let path = args.next().ok_or(usage)?;
+    let code = intcode::parse_program(&fs::read_to_string(path)?)?;
+    let mut inputs = args
+        .map(|arg| arg.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter();
+
+    let mut vm = VM::new(code);
+    vm.start_coverage();
+
+    let result = vm.run_with(&mut FnIo::new(|| inputs.next(), |n| eprintln!("{}", n)));
+
+    let coverage = vm.stop_coverage().unwrap();
+    if html {
+        print!("{}", coverage.html(vm.code()));
+    } else {
+        print!("{}", coverage.listing(vm.code()));
+    }
+
+    Ok(result?)
+}
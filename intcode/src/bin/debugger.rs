@@ -0,0 +1,325 @@
+use intcode::disasm::{self, Item};
+use intcode::{Access, ExecutionStatus, OpCode, Snapshot, VM};
+use std::collections::{HashSet, VecDeque};
+use std::convert::TryFrom;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+const HELP: &str = "\
+step [n]               execute n instructions (default 1)
+continue               run until a breakpoint, halt or missing input
+break <addr|mnemonic>  stop before an address or any instruction of a kind
+delete <addr|mnemonic> remove a breakpoint
+breakpoints            list breakpoints
+watch <addr> [r|w|rw]  stop when an address is read or written (default rw)
+unwatch <addr> [r|w|rw] remove a watchpoint
+watchpoints            list watchpoints
+input <n>...           queue values for the program to read
+regs                   show ip, relative base and queued input
+examine <addr> [n]     show n words of memory (default 1)
+extra                  show nonzero memory past the end of the program
+list [addr] [n]        disassemble n instructions (default from ip, 10)
+save <file>            write a snapshot of the VM to a file
+load <file>            restore the VM from a snapshot file
+quit                   exit the debugger
+
+Commands can be abbreviated to their first letter, and an empty line repeats
+the previous command.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Breakpoint {
+    Address(usize),
+    OpCode(OpCode),
+}
+
+impl FromStr for Breakpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(address) = s.parse() {
+            Ok(Breakpoint::Address(address))
+        } else {
+            OpCode::from_mnemonic(s)
+                .map(Breakpoint::OpCode)
+                .ok_or_else(|| format!("`{}` is neither an address nor a mnemonic", s))
+        }
+    }
+}
+
+fn parse_accesses(s: Option<&&str>) -> Result<&'static [Access], String> {
+    match s.copied() {
+        Some("r") => Ok(&[Access::Read]),
+        Some("w") => Ok(&[Access::Write]),
+        Some("rw") | None => Ok(&[Access::Read, Access::Write]),
+        Some(s) => Err(format!("Invalid access `{}`, expected r, w or rw", s)),
+    }
+}
+
+struct Debugger {
+    vm: VM,
+    breakpoints: HashSet<Breakpoint>,
+    inputs: VecDeque<i64>,
+}
+
+impl Debugger {
+    fn at_breakpoint(&self) -> bool {
+        let ip = self.vm.ip();
+        let opcode = OpCode::try_from(self.vm.get(ip) % 100);
+
+        self.breakpoints.contains(&Breakpoint::Address(ip))
+            || opcode.is_ok_and(|op| self.breakpoints.contains(&Breakpoint::OpCode(op)))
+    }
+
+    /// Executes one instruction, returning whether execution can carry on.
+    fn step(&mut self) -> bool {
+        let mut input = self.inputs.front().copied();
+        let had_input = input.is_some();
+
+        let status = match self.vm.step(&mut input) {
+            Ok(status) => status,
+            Err(e) => {
+                println!("error: {}", e);
+                return false;
+            }
+        };
+
+        if had_input && input.is_none() {
+            self.inputs.pop_front();
+        }
+
+        match status {
+            None => true,
+            Some(ExecutionStatus::Output(n)) => {
+                println!("output: {}", n);
+                true
+            }
+            Some(ExecutionStatus::AwaitingInput) => {
+                println!("awaiting input (queue some with `input <n>`)");
+                false
+            }
+            Some(ExecutionStatus::Complete) => {
+                println!("halted");
+                false
+            }
+            Some(ExecutionStatus::OutOfFuel) => {
+                println!("out of fuel");
+                false
+            }
+            Some(ExecutionStatus::Watchpoint(hits)) => {
+                for hit in hits {
+                    println!("watchpoint: {}", hit);
+                }
+                false
+            }
+        }
+    }
+
+    fn run(&mut self, max_steps: Option<usize>) {
+        let mut steps = 0;
+
+        while self.step() {
+            steps += 1;
+
+            if max_steps.is_some_and(|max| steps >= max) {
+                break;
+            }
+            if self.at_breakpoint() {
+                println!("breakpoint at {}", self.vm.ip());
+                break;
+            }
+        }
+
+        self.list(self.vm.ip(), 1);
+    }
+
+    fn list(&self, mut address: usize, count: usize) {
+        let code = self.vm.code();
+
+        for _ in 0..count {
+            if address >= code.len() {
+                break;
+            }
+
+            let item =
+                disasm::decode(&code[address..]).unwrap_or_else(|| Item::Data(vec![code[address]]));
+            let marker = if address == self.vm.ip() { "=>" } else { "  " };
+            let line = disasm::Line { address, item };
+
+            println!("{} {}", marker, line);
+            address = address.saturating_add(line.item.len());
+        }
+    }
+
+    fn examine(&self, address: usize, count: usize) {
+        for address in (0..count).map_while(|n| address.checked_add(n)) {
+            println!("{:04}: {}", address, self.vm.get(address));
+        }
+    }
+
+    fn regs(&self) {
+        println!("ip:            {}", self.vm.ip());
+        println!("relative base: {}", self.vm.relative_base());
+        println!("queued input:  {:?}", self.inputs);
+    }
+
+    fn extra(&self) {
+        for (address, value) in self.vm.extra_memory() {
+            println!("{:04}: {}", address, value);
+        }
+    }
+
+    fn execute(&mut self, command: &str, args: &[&str]) -> Result<bool, String> {
+        macro_rules! arg {
+            ($n:expr, $default:expr) => {
+                match args.get($n) {
+                    Some(arg) => arg
+                        .parse()
+                        .map_err(|_| format!("Invalid argument `{}`", arg))?,
+                    None => $default,
+                }
+            };
+        }
+
+        match command {
+            "s" | "step" => self.run(Some(arg!(0, 1))),
+            "c" | "continue" => self.run(None),
+
+            "b" | "break" => {
+                self.breakpoints
+                    .insert(args.first().ok_or("Missing breakpoint")?.parse()?);
+            }
+            "d" | "delete" => {
+                let breakpoint = args.first().ok_or("Missing breakpoint")?.parse()?;
+                if !self.breakpoints.remove(&breakpoint) {
+                    return Err("No such breakpoint".into());
+                }
+            }
+            "breakpoints" => {
+                for breakpoint in &self.breakpoints {
+                    match breakpoint {
+                        Breakpoint::Address(address) => println!("{:04}", address),
+                        Breakpoint::OpCode(opcode) => println!("{}", opcode.mnemonic()),
+                    }
+                }
+            }
+
+            "w" | "watch" => {
+                let address = args.first().ok_or("Missing address")?;
+                let address = address
+                    .parse()
+                    .map_err(|_| format!("Invalid address `{}`", address))?;
+                for access in parse_accesses(args.get(1))? {
+                    self.vm.watch(address, *access);
+                }
+            }
+            "unwatch" => {
+                let address = args.first().ok_or("Missing address")?;
+                let address = address
+                    .parse()
+                    .map_err(|_| format!("Invalid address `{}`", address))?;
+                let mut removed = false;
+                for access in parse_accesses(args.get(1))? {
+                    removed |= self.vm.unwatch(address, *access);
+                }
+                if !removed {
+                    return Err("No such watchpoint".into());
+                }
+            }
+            "watchpoints" => {
+                let mut watchpoints = self.vm.watchpoints().iter().collect::<Vec<_>>();
+                watchpoints.sort_by_key(|watchpoint| watchpoint.address);
+
+                for watchpoint in watchpoints {
+                    let access = match watchpoint.access {
+                        Access::Read => "read",
+                        Access::Write => "write",
+                    };
+                    println!("{:04} {}", watchpoint.address, access);
+                }
+            }
+
+            "i" | "input" => {
+                for arg in args {
+                    self.inputs.push_back(
+                        arg.parse()
+                            .map_err(|_| format!("Invalid input `{}`", arg))?,
+                    );
+                }
+            }
+
+            "r" | "regs" => self.regs(),
+            "x" | "examine" => self.examine(arg!(0, self.vm.ip()), arg!(1, 1)),
+            "e" | "extra" => self.extra(),
+            "l" | "list" => self.list(arg!(0, self.vm.ip()), arg!(1, 10)),
+
+            "save" => {
+                let path = args.first().ok_or("Missing file name")?;
+                fs::write(path, self.vm.snapshot().to_string()).map_err(|e| e.to_string())?;
+            }
+            "load" => {
+                let path = args.first().ok_or("Missing file name")?;
+                let snapshot = fs::read_to_string(path)
+                    .map_err(|e| e.to_string())?
+                    .parse::<Snapshot>()
+                    .map_err(|e| e.to_string())?;
+                self.vm.restore(&snapshot);
+                self.list(self.vm.ip(), 1);
+            }
+
+            "h" | "help" => println!("{}", HELP),
+            "q" | "quit" => return Ok(false),
+
+            _ => return Err(format!("Unknown command `{}`, try `help`", command)),
+        }
+
+        Ok(true)
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = env::args().skip(1);
+    let path = args.next().ok_or("Usage: debugger <program> [input...]")?;
+    let code = intcode::parse_program(&fs::read_to_string(path)?)?;
+
+    let mut debugger = Debugger {
+        vm: VM::new(code),
+        breakpoints: HashSet::new(),
+        inputs: args
+            .map(|arg| arg.parse::<i64>())
+            .collect::<Result<_, _>>()?,
+    };
+
+    let stdin = io::stdin();
+    let mut last_command = String::new();
+
+    debugger.list(0, 1);
+
+    loop {
+        print!("(icdb) ");
+        io::stdout().flush()?;
+
+        let mut buf = String::new();
+        if stdin.read_line(&mut buf)? == 0 {
+            break;
+        }
+        if buf.trim().is_empty() {
+            buf = last_command.clone();
+        }
+
+        let words = buf.split_whitespace().collect::<Vec<_>>();
+        if words.is_empty() {
+            continue;
+        }
+
+        match debugger.execute(words[0], &words[1..]) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => println!("{}", e),
+        }
+        last_command = buf;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,13 @@
+use intcode::decompile::decompile;
+use std::env;
+use std::fs;
+
+/// Prints a program as C-like pseudo-code.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = env::args().nth(1).ok_or("Usage: decompile <program>")?;
+    let code = intcode::parse_program(&fs::read_to_string(path)?)?;
+
+    print!("{}", decompile(&code));
+
+    Ok(())
+}
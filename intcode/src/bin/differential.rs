@@ -0,0 +1,37 @@
+use intcode::differential::{self, Subset};
+use std::env;
+
+/// Generates random programs for each opcode subset and checks that every
+/// way of running them on the VM agrees with the reference interpreter.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = env::args().skip(1);
+    let count = match args.next() {
+        Some(arg) => arg.parse::<u64>()?,
+        None => 1000,
+    };
+    let first = match args.next() {
+        Some(arg) => arg.parse::<u64>()?,
+        None => 0,
+    };
+
+    let mut found = 0;
+    for subset in [Subset::Day5, Subset::Day9] {
+        for seed in first..first + count {
+            for divergence in differential::check(subset, seed) {
+                println!("{}\n", divergence);
+                found += 1;
+            }
+        }
+    }
+
+    println!(
+        "Checked {} programs from seed {}: {} divergences",
+        count * 2,
+        first,
+        found
+    );
+    if found > 0 {
+        return Err("The VM diverged from the reference interpreter".into());
+    }
+    Ok(())
+}
@@ -0,0 +1,17 @@
+use intcode::disasm::disassemble;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = env::args().nth(1).ok_or("Usage: disasm <program>")?;
+    let code = intcode::parse_program(&fs::read_to_string(path)?)?;
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    for line in disassemble(&code) {
+        writeln!(stdout, "{}", line)?;
+    }
+
+    Ok(())
+}
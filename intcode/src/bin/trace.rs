@@ -0,0 +1,35 @@
+use intcode::{ExecutionStatus, VM};
+use std::env;
+use std::fs;
+use std::io;
+
+/// Runs a program with the inputs given on the command line, writing a trace
+/// of every instruction to stdout and the program's output to stderr.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = env::args().skip(1);
+    let path = args.next().ok_or("Usage: trace <program> [input...]")?;
+    let code = intcode::parse_program(&fs::read_to_string(path)?)?;
+    let mut inputs = args
+        .map(|arg| arg.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter();
+
+    let mut vm = VM::new(code);
+    vm.trace_to(io::BufWriter::new(io::stdout()));
+
+    let mut input = None;
+    loop {
+        match vm.resume(input.take())? {
+            ExecutionStatus::Output(n) => eprintln!("{}", n),
+            ExecutionStatus::AwaitingInput => {
+                input = Some(inputs.next().ok_or("Program wants more input")?);
+            }
+            ExecutionStatus::Complete => break,
+            ExecutionStatus::OutOfFuel | ExecutionStatus::Watchpoint(_) => unreachable!(),
+        }
+    }
+
+    vm.stop_tracing();
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use intcode::{FnIo, WxPolicy, VM};
+use std::env;
+use std::fs;
+
+/// Runs a program with the inputs given on the command line and lists every
+/// write it makes to an instruction it has already executed. With
+/// `--strict`, the first such write stops the program instead. The writes
+/// are listed even if the program fails. The program's output goes to
+/// stderr.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let usage = "Usage: wx [--strict] <program> [input...]";
+    let mut args = env::args().skip(1).peekable();
+    let policy = match args.next_if(|arg| arg == "--strict") {
+        Some(_) => WxPolicy::Strict,
+        None => WxPolicy::Log,
+    };
+    let path = args.next().ok_or(usage)?;
+    let code = intcode::parse_program(&fs::read_to_string(path)?)?;
+    let mut inputs = args
+        .map(|arg| arg.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter();
+
+    let mut vm = VM::new(code);
+    vm.start_code_write_detection(policy);
+
+    let result = vm.run_with(&mut FnIo::new(|| inputs.next(), |n| eprintln!("{}", n)));
+
+    for write in vm.stop_code_write_detection().unwrap() {
+        println!("{}", write);
+    }
+
+    Ok(result?)
+}
@@ -0,0 +1,379 @@
+//! Static control-flow graphs of programs.
+//!
+//! The graph is built from the program image alone, following every path
+//! from address 0. Jumps with immediate targets become edges. A jump whose
+//! target comes from memory is indirect: where it goes can't be known without
+//! running the program, so its block is flagged instead. Programs that
+//! modify their own instructions can of course go places the graph doesn't
+//! show.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use crate::disasm::{self, Item, Line, Operand};
+use crate::opcode::{OpCode, ParamMode};
+
+/// How a block ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockEnd {
+    /// Runs straight into the block after it.
+    Next,
+    Jump,
+    /// A jump whose target is read from memory.
+    IndirectJump,
+    Halt,
+    /// Runs into a word at this address that isn't an instruction, or off
+    /// the end of the program.
+    Invalid(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    Taken,
+    NotTaken,
+    /// From a block that runs straight into another.
+    Next,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+}
+
+/// A run of instructions that is only ever entered at the top.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub start: usize,
+    pub lines: Vec<Line>,
+    pub end: BlockEnd,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cfg {
+    /// The blocks reachable from address 0, by start address.
+    pub blocks: BTreeMap<usize, Block>,
+    pub edges: Vec<Edge>,
+}
+
+/// Where execution can go after an instruction.
+struct Flow {
+    /// The instruction after it, if execution can carry on there.
+    next: Option<usize>,
+    /// The target of a jump that can be taken, if it is known.
+    target: Option<usize>,
+    /// Set for instructions that end a block.
+    end: Option<BlockEnd>,
+}
+
+fn decode(code: &[i64], address: usize) -> Option<Item> {
+    code.get(address..).and_then(disasm::decode)
+}
+
+fn flow(address: usize, item: &Option<Item>) -> Flow {
+    let (opcode, operands) = match item {
+        Some(Item::Instruction { opcode, operands }) => (*opcode, operands),
+        _ => {
+            return Flow {
+                next: None,
+                target: None,
+                end: Some(BlockEnd::Invalid(address)),
+            }
+        }
+    };
+    let next = address + opcode.effect();
+
+    let when = match opcode {
+        OpCode::TJmp => true,
+        OpCode::FJmp => false,
+        OpCode::Halt => {
+            return Flow {
+                next: None,
+                target: None,
+                end: Some(BlockEnd::Halt),
+            }
+        }
+        _ => {
+            return Flow {
+                next: Some(next),
+                target: None,
+                end: None,
+            }
+        }
+    };
+
+    let taken = match operands[0] {
+        Operand {
+            mode: ParamMode::Immediate,
+            value,
+        } => Some((value != 0) == when),
+        _ => None,
+    };
+    let (target, end) = match operands[1] {
+        // A jump to a negative address fails, so it has nowhere to go
+        Operand {
+            mode: ParamMode::Immediate,
+            value,
+        } => (Some(value as usize).filter(|_| value >= 0), BlockEnd::Jump),
+        _ if taken == Some(false) => (None, BlockEnd::Jump),
+        _ => (None, BlockEnd::IndirectJump),
+    };
+
+    Flow {
+        next: Some(next).filter(|_| taken != Some(true)),
+        target: target.filter(|_| taken != Some(false)),
+        end: Some(end),
+    }
+}
+
+impl Cfg {
+    /// Builds the graph of the blocks reachable from address 0 of `code`.
+    pub fn new(code: &[i64]) -> Self {
+        Cfg::with_entries(code, [0])
+    }
+
+    /// Builds the graph of the blocks reachable from any of `entries`, such
+    /// as the places indirect jumps are known to go.
+    pub fn with_entries(code: &[i64], entries: impl IntoIterator<Item = usize>) -> Self {
+        // Find where blocks start by following every path through the program
+        let mut leaders = entries.into_iter().collect::<BTreeSet<_>>();
+        let mut seen = BTreeSet::new();
+        let mut work = leaders.iter().copied().collect::<Vec<_>>();
+
+        while let Some(address) = work.pop() {
+            if !seen.insert(address) {
+                continue;
+            }
+
+            let flow = flow(address, &decode(code, address));
+            if flow.end.is_some() {
+                leaders.extend(flow.next);
+                leaders.extend(flow.target);
+            }
+            work.extend(flow.next);
+            work.extend(flow.target);
+        }
+
+        // Then walk each block from its start up to the next
+        let mut cfg = Cfg::default();
+
+        for start in &leaders {
+            let mut lines = Vec::new();
+            let mut address = *start;
+
+            let end = loop {
+                let item = decode(code, address);
+                let flow = flow(address, &item);
+                if let Some(item) = item {
+                    lines.push(Line { address, item });
+                }
+
+                let edge = |to, kind| Edge {
+                    from: *start,
+                    to,
+                    kind,
+                };
+
+                if let Some(end) = flow.end {
+                    let not_taken = match end {
+                        BlockEnd::Jump | BlockEnd::IndirectJump => EdgeKind::NotTaken,
+                        _ => EdgeKind::Next,
+                    };
+                    cfg.edges
+                        .extend(flow.target.map(|to| edge(to, EdgeKind::Taken)));
+                    cfg.edges.extend(flow.next.map(|to| edge(to, not_taken)));
+                    break end;
+                }
+
+                let next = flow.next.unwrap();
+                if leaders.contains(&next) {
+                    cfg.edges.push(edge(next, EdgeKind::Next));
+                    break BlockEnd::Next;
+                }
+                address = next;
+            };
+
+            cfg.blocks.insert(
+                *start,
+                Block {
+                    start: *start,
+                    lines,
+                    end,
+                },
+            );
+        }
+
+        cfg
+    }
+
+    /// The addresses of the jumps whose targets are read from memory.
+    pub fn indirect_jumps(&self) -> Vec<usize> {
+        self.blocks
+            .values()
+            .filter(|block| block.end == BlockEnd::IndirectJump)
+            .filter_map(|block| block.lines.last())
+            .map(|line| line.address)
+            .collect()
+    }
+
+    /// The graph in Graphviz's DOT language, with a node per block listing
+    /// its instructions. Blocks ending in indirect jumps are drawn in red,
+    /// and those that run into something that isn't an instruction are
+    /// dashed.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+
+        dot.push_str("digraph cfg {\n");
+        dot.push_str("    node [shape=box, fontname=\"monospace\"];\n");
+
+        for block in self.blocks.values() {
+            let mut label = block
+                .lines
+                .iter()
+                .map(|line| format!("{}\\l", line))
+                .collect::<String>();
+            let style = match block.end {
+                BlockEnd::IndirectJump => ", color=red",
+                BlockEnd::Invalid(address) => {
+                    write!(label, "{:04}: ?\\l", address).unwrap();
+                    ", style=dashed"
+                }
+                _ => "",
+            };
+
+            writeln!(dot, "    b{} [label=\"{}\"{}];", block.start, label, style).unwrap();
+        }
+
+        for edge in &self.edges {
+            let style = match edge.kind {
+                EdgeKind::Taken => "",
+                EdgeKind::NotTaken => " [style=dashed]",
+                EdgeKind::Next => " [style=dotted]",
+            };
+            writeln!(dot, "    b{} -> b{}{};", edge.from, edge.to, style).unwrap();
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ends(cfg: &Cfg) -> Vec<(usize, usize, BlockEnd)> {
+        cfg.blocks
+            .values()
+            .map(|block| (block.start, block.lines.len(), block.end))
+            .collect()
+    }
+
+    fn edges(cfg: &Cfg) -> Vec<(usize, usize, EdgeKind)> {
+        cfg.edges
+            .iter()
+            .map(|edge| (edge.from, edge.to, edge.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_loop() {
+        // Counts down from the input, printing each number
+        let code = [3, 13, 4, 13, 1001, 13, -1, 13, 1005, 13, 2, 99, 0, 0];
+        let cfg = Cfg::new(&code);
+
+        assert_eq!(
+            ends(&cfg),
+            vec![
+                (0, 1, BlockEnd::Next),
+                (2, 3, BlockEnd::Jump),
+                (11, 1, BlockEnd::Halt),
+            ]
+        );
+        assert_eq!(
+            edges(&cfg),
+            vec![
+                (0, 2, EdgeKind::Next),
+                (2, 2, EdgeKind::Taken),
+                (2, 11, EdgeKind::NotTaken),
+            ]
+        );
+        assert!(cfg.indirect_jumps().is_empty());
+    }
+
+    #[test]
+    fn test_known_predicates() {
+        // Always jumps over the data, and never takes the second jump
+        let code = [1105, 1, 5, 12345, 0, 1106, 1, 0, 99];
+        let cfg = Cfg::new(&code);
+
+        assert_eq!(
+            ends(&cfg),
+            vec![
+                (0, 1, BlockEnd::Jump),
+                (5, 1, BlockEnd::Jump),
+                (8, 1, BlockEnd::Halt),
+            ]
+        );
+        assert_eq!(
+            edges(&cfg),
+            vec![(0, 5, EdgeKind::Taken), (5, 8, EdgeKind::NotTaken)]
+        );
+    }
+
+    #[test]
+    fn test_indirect_and_invalid() {
+        // Calls a subroutine that returns through an address on the stack
+        let code = [
+            109, 20, 21101, 0, 10, 0, 1105, 1, 12, 0, 1, 2, 2101, 5, 0, 0, 2105, 1, 0,
+        ];
+        let cfg = Cfg::new(&code);
+
+        assert_eq!(
+            ends(&cfg),
+            vec![(0, 3, BlockEnd::Jump), (12, 2, BlockEnd::IndirectJump)]
+        );
+        assert_eq!(cfg.indirect_jumps(), vec![16]);
+
+        // Adding where it returns to, which overlaps the subroutine
+        let cfg = Cfg::with_entries(&code, [0, 10]);
+        assert_eq!(
+            ends(&cfg),
+            vec![
+                (0, 3, BlockEnd::Jump),
+                (10, 1, BlockEnd::Invalid(14)),
+                (12, 2, BlockEnd::IndirectJump),
+            ]
+        );
+
+        let cfg = Cfg::new(&[1101, 1, 1, 0, 7]);
+        assert_eq!(ends(&cfg), vec![(0, 1, BlockEnd::Invalid(4))]);
+    }
+
+    #[test]
+    fn test_dot() {
+        let code = [3, 9, 1006, 9, 7, 104, 1, 99, 0, 0];
+
+        assert_eq!(
+            Cfg::new(&code).to_dot(),
+            concat!(
+                "digraph cfg {\n",
+                "    node [shape=box, fontname=\"monospace\"];\n",
+                "    b0 [label=\"0000: in [9]\\l0002: jf [9], #7\\l\"];\n",
+                "    b5 [label=\"0005: out #1\\l\"];\n",
+                "    b7 [label=\"0007: hlt\\l\"];\n",
+                "    b0 -> b7;\n",
+                "    b0 -> b5 [style=dashed];\n",
+                "    b5 -> b7 [style=dotted];\n",
+                "}\n",
+            )
+        );
+
+        let dot = Cfg::new(&[1, 0, 0, 0, 1105, 1, 0, 2105, 1, 0]).to_dot();
+        assert!(dot.contains("b0 [label=\"0000: add [0], [0], [0]\\l0004: jt #1, #0\\l\"];"));
+
+        let dot = Cfg::new(&[1106, 0, 3, 1, 0, 0]).to_dot();
+        assert!(dot.contains("b3 [label=\"0003: ?\\l\", style=dashed];"));
+    }
+}
@@ -0,0 +1,248 @@
+//! Recording which parts of a program a run actually used.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::disasm::{self, Item};
+
+/// The addresses a program executed, read and wrote while coverage was being
+/// recorded.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    /// The addresses of the instructions executed.
+    pub executed: BTreeSet<usize>,
+    /// Addresses read through positional or relative parameters.
+    pub read: BTreeSet<usize>,
+    pub written: BTreeSet<usize>,
+}
+
+/// A line of the annotated listing.
+struct CoveredLine {
+    address: usize,
+    item: Item,
+    executed: bool,
+    read: bool,
+    written: bool,
+}
+
+impl CoveredLine {
+    fn flags(&self) -> String {
+        [(self.executed, 'x'), (self.read, 'r'), (self.written, 'w')]
+            .iter()
+            .map(|(set, flag)| if *set { *flag } else { '-' })
+            .collect()
+    }
+
+    fn classes(&self) -> Vec<&'static str> {
+        let kind = match (&self.item, self.executed) {
+            (_, true) => "executed",
+            (Item::Instruction { .. }, false) => "missed",
+            (Item::Data(_), false) => "data",
+        };
+
+        let mut classes = vec![kind];
+        if self.read {
+            classes.push("read");
+        }
+        if self.written {
+            classes.push("written");
+        }
+        classes
+    }
+}
+
+impl Coverage {
+    /// Splits `code` into instructions and data like the disassembler, except
+    /// that executed instructions are always decoded where they start, and
+    /// data is shown a word at a time.
+    fn lines(&self, code: &[i64]) -> Vec<CoveredLine> {
+        let mut lines = Vec::new();
+        let mut address = 0;
+
+        while address < code.len() {
+            let item = match disasm::decode(&code[address..]) {
+                Some(item)
+                    if self.executed.contains(&address)
+                        || self
+                            .executed
+                            .range(address + 1..address + item.len())
+                            .next()
+                            .is_none() =>
+                {
+                    item
+                }
+                _ => Item::Data(vec![code[address]]),
+            };
+            let words = address..address + item.len();
+
+            lines.push(CoveredLine {
+                address,
+                executed: self.executed.contains(&address),
+                read: self.read.range(words.clone()).next().is_some(),
+                written: self.written.range(words.clone()).next().is_some(),
+                item,
+            });
+            address = words.end;
+        }
+
+        lines
+    }
+
+    fn summary(&self, code: &[i64], lines: &[CoveredLine]) -> Vec<String> {
+        let instructions = lines
+            .iter()
+            .filter(|line| matches!(line.item, Item::Instruction { .. }))
+            .count();
+        let executed = lines.iter().filter(|line| line.executed).count();
+        let in_code = |addresses: &BTreeSet<usize>| addresses.range(..code.len()).count();
+        let past_end = |addresses: &BTreeSet<usize>| addresses.range(code.len()..).count();
+
+        vec![
+            format!("{} of {} instructions executed", executed, instructions),
+            format!(
+                "words read: {} in the program, {} past its end",
+                in_code(&self.read),
+                past_end(&self.read)
+            ),
+            format!(
+                "words written: {} in the program, {} past its end",
+                in_code(&self.written),
+                past_end(&self.written)
+            ),
+        ]
+    }
+
+    /// A listing of `code` with each line marked `x` if it was executed, `r`
+    /// if any of its words were read and `w` if any were written.
+    pub fn listing(&self, code: &[i64]) -> String {
+        let lines = self.lines(code);
+        let mut listing = String::new();
+
+        for summary in self.summary(code, &lines) {
+            writeln!(listing, "{}", summary).unwrap();
+        }
+        for line in &lines {
+            writeln!(
+                listing,
+                "{} {:04}: {}",
+                line.flags(),
+                line.address,
+                line.item
+            )
+            .unwrap();
+        }
+
+        listing
+    }
+
+    /// The listing as a standalone HTML page, with instructions that were
+    /// never executed highlighted.
+    pub fn html(&self, code: &[i64]) -> String {
+        let lines = self.lines(code);
+        let mut html = String::new();
+
+        html.push_str(concat!(
+            "<!DOCTYPE html>\n",
+            "<html>\n",
+            "<head>\n",
+            "<meta charset=\"utf-8\">\n",
+            "<title>Intcode coverage</title>\n",
+            "<style>\n",
+            ".executed { background: #cfc; }\n",
+            ".missed { background: #fcc; }\n",
+            ".data { color: #666; }\n",
+            ".read { font-weight: bold; }\n",
+            ".written { text-decoration: underline; }\n",
+            "</style>\n",
+            "</head>\n",
+            "<body>\n",
+        ));
+        for summary in self.summary(code, &lines) {
+            writeln!(html, "<p>{}</p>", summary).unwrap();
+        }
+        html.push_str("<pre>\n");
+        for line in &lines {
+            writeln!(
+                html,
+                "<span class=\"{}\" title=\"{}\">{:04}: {}</span>",
+                line.classes().join(" "),
+                line.flags(),
+                line.address,
+                line.item
+            )
+            .unwrap();
+        }
+        html.push_str("</pre>\n</body>\n</html>\n");
+
+        html
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Reads a value and prints it if it's nonzero, or prints 0 otherwise.
+    const CODE: [i64; 13] = [3, 12, 1005, 12, 8, 104, 0, 99, 4, 12, 99, 0, 0];
+
+    fn coverage() -> Coverage {
+        Coverage {
+            executed: vec![0, 2, 8, 10].into_iter().collect(),
+            read: vec![12, 500].into_iter().collect(),
+            written: vec![12].into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn test_listing() {
+        assert_eq!(
+            coverage().listing(&CODE),
+            concat!(
+                "4 of 6 instructions executed\n",
+                "words read: 1 in the program, 1 past its end\n",
+                "words written: 1 in the program, 0 past its end\n",
+                "x-- 0000: in [12]\n",
+                "x-- 0002: jt [12], #8\n",
+                "--- 0005: out #0\n",
+                "--- 0007: hlt\n",
+                "x-- 0008: out [12]\n",
+                "x-- 0010: hlt\n",
+                "--- 0011: data 0\n",
+                "-rw 0012: data 0\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_executed_data() {
+        // Jumps into the middle of what looks like an add
+        let coverage = Coverage {
+            executed: vec![0, 4, 6].into_iter().collect(),
+            ..Coverage::default()
+        };
+
+        assert_eq!(
+            coverage.listing(&[1105, 1, 4, 1101, 104, 7, 99]),
+            concat!(
+                "3 of 3 instructions executed\n",
+                "words read: 0 in the program, 0 past its end\n",
+                "words written: 0 in the program, 0 past its end\n",
+                "x-- 0000: jt #1, #4\n",
+                "--- 0003: data 1101\n",
+                "x-- 0004: out #7\n",
+                "x-- 0006: hlt\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_html() {
+        let html = coverage().html(&CODE);
+
+        assert!(html.contains("<span class=\"executed\" title=\"x--\">0000: in [12]</span>\n"));
+        assert!(html.contains("<span class=\"missed\" title=\"---\">0005: out #0</span>\n"));
+        assert!(
+            html.contains("<span class=\"data read written\" title=\"-rw\">0012: data 0</span>\n")
+        );
+    }
+}
@@ -0,0 +1,1093 @@
+//! Decompiling programs into C-like pseudo-code.
+//!
+//! Programs are split into functions following the calling convention of
+//! the puzzle inputs: the caller stores the address to return to at `[r+0]`
+//! and any arguments at `[r+1]` onwards, then jumps to the function, which
+//! moves the relative base past its frame with `arb` and moves it back before
+//! jumping through `[r+0]`. Relative operands are named after the part of the
+//! frame they refer to, comparisons are folded into the jumps that test them,
+//! and conditional jumps become `if` and `do`-`while` statements when the
+//! code they skip or repeat can't be entered any other way. Anything else is
+//! left as a `goto`.
+//!
+//! The result is for reading rather than compiling. It assumes the flags
+//! comparisons store aren't read again, and of the ways a program can modify
+//! itself only understands patching an operand of a later instruction in the
+//! same block, which is shown as indexing `mem`.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::convert::TryFrom;
+use std::fmt::{self, Write};
+
+use crate::cfg::{Block, BlockEnd, Cfg};
+use crate::disasm::{Item, Line, Operand};
+use crate::opcode::{OpCode, ParamMode};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Var {
+    /// The word at a fixed address.
+    Global(usize),
+    /// A word of the frame stored by the caller, numbered from the return
+    /// address.
+    Arg(i64),
+    /// A word of the frame the function uses for itself.
+    Local(i64),
+    /// A word past the end of the frame, used to pass arguments to the
+    /// functions it calls and to get their results back.
+    Out(i64),
+    ReturnAddress,
+    /// A word before the start of the frame, by offset from the base.
+    Relative(i64),
+}
+
+impl fmt::Display for Var {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Var::Global(address) => write!(f, "g{}", address),
+            Var::Arg(n) => write!(f, "arg{}", n),
+            Var::Local(n) => write!(f, "local{}", n),
+            Var::Out(n) => write!(f, "out{}", n),
+            Var::ReturnAddress => write!(f, "retaddr"),
+            Var::Relative(offset) => write!(f, "rb[{}]", offset),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Mul,
+    Add,
+    Sub,
+    Lt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+impl Op {
+    fn symbol(self) -> &'static str {
+        match self {
+            Op::Mul => "*",
+            Op::Add => "+",
+            Op::Sub => "-",
+            Op::Lt => "<",
+            Op::Ge => ">=",
+            Op::Eq => "==",
+            Op::Ne => "!=",
+        }
+    }
+
+    fn precedence(self) -> u8 {
+        match self {
+            Op::Mul => 4,
+            Op::Add | Op::Sub => 3,
+            Op::Lt | Op::Ge => 2,
+            Op::Eq | Op::Ne => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Const(i64),
+    Var(Var),
+    /// The relative base.
+    Base,
+    Input,
+    /// The word at a computed address.
+    Mem(Box<Expr>),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(Op, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Binary(op, ..) => op.precedence(),
+            Expr::Neg(_) | Expr::Not(_) => 5,
+            _ => 6,
+        }
+    }
+
+    fn vars(&self, vars: &mut BTreeSet<Var>) {
+        match self {
+            Expr::Var(var) => {
+                vars.insert(*var);
+            }
+            Expr::Mem(e) | Expr::Neg(e) | Expr::Not(e) => e.vars(vars),
+            Expr::Binary(_, left, right) => {
+                left.vars(vars);
+                right.vars(vars);
+            }
+            Expr::Const(_) | Expr::Base | Expr::Input => {}
+        }
+    }
+
+    fn write_operand(&self, f: &mut fmt::Formatter, parens: bool) -> fmt::Result {
+        if parens {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Const(n) => write!(f, "{}", n),
+            Expr::Var(var) => write!(f, "{}", var),
+            Expr::Base => write!(f, "rb"),
+            Expr::Input => write!(f, "input()"),
+            Expr::Mem(address) => write!(f, "mem[{}]", address),
+            Expr::Neg(e) => {
+                write!(f, "-")?;
+                e.write_operand(
+                    f,
+                    e.precedence() < 5 || matches!(**e, Expr::Const(n) if n < 0),
+                )
+            }
+            Expr::Not(e) => {
+                write!(f, "!")?;
+                e.write_operand(f, e.precedence() < 5)
+            }
+            Expr::Binary(op, left, right) => {
+                left.write_operand(f, left.precedence() < op.precedence())?;
+                write!(f, " {} ", op.symbol())?;
+                right.write_operand(f, right.precedence() <= op.precedence())
+            }
+        }
+    }
+}
+
+fn binary(op: Op, left: Expr, right: Expr) -> Expr {
+    Expr::Binary(op, Box::new(left), Box::new(right))
+}
+
+fn add(left: Expr, right: Expr) -> Expr {
+    match (left, right) {
+        (Expr::Const(a), Expr::Const(b)) if a.checked_add(b).is_some() => Expr::Const(a + b),
+        (Expr::Const(0), e) | (e, Expr::Const(0)) => e,
+        // Constants go on the right, and negative ones are subtracted
+        (Expr::Const(n), e) if !matches!(e, Expr::Const(_)) => add(e, Expr::Const(n)),
+        (e, Expr::Const(n)) if n < 0 && n != i64::MIN => binary(Op::Sub, e, Expr::Const(-n)),
+        (left, right) => binary(Op::Add, left, right),
+    }
+}
+
+fn mul(left: Expr, right: Expr) -> Expr {
+    match (left, right) {
+        (Expr::Const(a), Expr::Const(b)) if a.checked_mul(b).is_some() => Expr::Const(a * b),
+        (Expr::Const(0), _) | (_, Expr::Const(0)) => Expr::Const(0),
+        (Expr::Const(1), e) | (e, Expr::Const(1)) => e,
+        (Expr::Const(-1), e) | (e, Expr::Const(-1)) => neg(e),
+        (Expr::Const(n), e) => binary(Op::Mul, e, Expr::Const(n)),
+        (left, right) => binary(Op::Mul, left, right),
+    }
+}
+
+fn neg(e: Expr) -> Expr {
+    match e {
+        Expr::Const(n) if n != i64::MIN => Expr::Const(-n),
+        Expr::Neg(e) => *e,
+        e => Expr::Neg(Box::new(e)),
+    }
+}
+
+fn compare(op: Op, left: Expr, right: Expr) -> Expr {
+    match (op, left, right) {
+        (Op::Lt, Expr::Const(a), Expr::Const(b)) => Expr::Const((a < b) as i64),
+        (Op::Eq, Expr::Const(a), Expr::Const(b)) => Expr::Const((a == b) as i64),
+        (op, left, right) => binary(op, left, right),
+    }
+}
+
+/// The condition that holds when `e` doesn't.
+fn not(e: Expr) -> Expr {
+    let inverse = |op| match op {
+        Op::Lt => Some(Op::Ge),
+        Op::Ge => Some(Op::Lt),
+        Op::Eq => Some(Op::Ne),
+        Op::Ne => Some(Op::Eq),
+        _ => None,
+    };
+
+    match e {
+        Expr::Const(n) => Expr::Const((n == 0) as i64),
+        Expr::Not(e) => *e,
+        Expr::Binary(op, left, right) if inverse(op).is_some() => {
+            Expr::Binary(inverse(op).unwrap(), left, right)
+        }
+        e => Expr::Not(Box::new(e)),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Stmt {
+    Label(usize),
+    Assign(Expr, Expr),
+    Output(Expr),
+    Call(usize, Vec<Expr>),
+    AdjustBase(Expr),
+    Return,
+    Halt,
+    Goto(usize),
+    GotoIndirect(Expr),
+    /// Runs into a word at this address that isn't an instruction.
+    Invalid(usize),
+    If(Expr, Vec<Stmt>),
+    DoWhile(Vec<Stmt>, Expr),
+}
+
+impl Stmt {
+    fn vars(&self, vars: &mut BTreeSet<Var>) {
+        match self {
+            Stmt::Assign(dest, value) => {
+                dest.vars(vars);
+                value.vars(vars);
+            }
+            Stmt::Output(e) | Stmt::AdjustBase(e) | Stmt::GotoIndirect(e) => e.vars(vars),
+            Stmt::Call(_, args) => args.iter().for_each(|arg| arg.vars(vars)),
+            Stmt::If(cond, body) | Stmt::DoWhile(body, cond) => {
+                cond.vars(vars);
+                body.iter().for_each(|stmt| stmt.vars(vars));
+            }
+            _ => {}
+        }
+    }
+
+    fn gotos(&self, targets: &mut BTreeSet<usize>) {
+        match self {
+            Stmt::Goto(target) => {
+                targets.insert(*target);
+            }
+            Stmt::If(_, body) | Stmt::DoWhile(body, _) => {
+                body.iter().for_each(|stmt| stmt.gotos(targets))
+            }
+            _ => {}
+        }
+    }
+
+    /// The statement as a single line, unless it's a label or holds other
+    /// statements.
+    fn simple(&self) -> Option<String> {
+        Some(match self {
+            Stmt::Assign(dest, Expr::Binary(op @ (Op::Add | Op::Sub | Op::Mul), left, right))
+                if **left == *dest =>
+            {
+                format!("{} {}= {};", dest, op.symbol(), right)
+            }
+            Stmt::Assign(dest, value) => format!("{} = {};", dest, value),
+            Stmt::Output(e) => format!("output({});", e),
+            Stmt::Call(target, args) => format!(
+                "{}({});",
+                function_name(*target),
+                args.iter()
+                    .map(Expr::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Stmt::AdjustBase(e) => format!("rb += {};", e),
+            Stmt::Return => "return;".to_string(),
+            Stmt::Halt => "halt();".to_string(),
+            Stmt::Goto(target) => format!("goto l{};", target),
+            Stmt::GotoIndirect(e @ Expr::Binary(..)) => format!("goto *({});", e),
+            Stmt::GotoIndirect(e) => format!("goto *{};", e),
+            Stmt::Invalid(address) => format!("// {:04}: not an instruction", address),
+            Stmt::Label(_) | Stmt::If(..) | Stmt::DoWhile(..) => return None,
+        })
+    }
+}
+
+fn function_name(entry: usize) -> String {
+    match entry {
+        0 => "main".to_string(),
+        _ => format!("f{}", entry),
+    }
+}
+
+/// How a block passes control on.
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    /// Carries on at the next address, including after a call.
+    Fall(usize),
+    Goto(usize),
+    /// Jumps to the target if the condition holds, and carries on otherwise.
+    Branch(Expr, usize, usize),
+    End,
+}
+
+impl Term {
+    fn successors(&self) -> Vec<usize> {
+        match self {
+            Term::Fall(next) | Term::Goto(next) => vec![*next],
+            Term::Branch(_, target, next) => vec![*target, *next],
+            Term::End => vec![],
+        }
+    }
+}
+
+/// The layout of a function's frame, for naming relative operands.
+struct Frame {
+    /// The amount the function moves the relative base by on entry.
+    size: i64,
+    /// The number of arguments it's called with.
+    args: i64,
+}
+
+impl Frame {
+    fn var(&self, offset: i64) -> Var {
+        let slot = offset.saturating_add(self.size);
+
+        if offset >= 0 {
+            Var::Out(offset)
+        } else if slot < 0 {
+            Var::Relative(offset)
+        } else if slot == 0 {
+            Var::ReturnAddress
+        } else if slot <= self.args {
+            Var::Arg(slot)
+        } else {
+            Var::Local(slot)
+        }
+    }
+}
+
+/// A block that ends by calling a function.
+struct Call {
+    target: usize,
+    /// The number of arguments stored just before the call.
+    args: i64,
+    /// The index of the line storing the return address.
+    link: usize,
+}
+
+fn instruction(line: &Line) -> Option<(OpCode, &[Operand])> {
+    match &line.item {
+        Item::Instruction { opcode, operands } => Some((*opcode, operands)),
+        Item::Data(_) => None,
+    }
+}
+
+fn end_of(block: &Block) -> usize {
+    block
+        .lines
+        .last()
+        .map_or(block.start, |line| line.address + line.item.len())
+}
+
+/// Whether a jump with this predicate is always taken.
+fn always_taken(opcode: OpCode, pred: &Operand) -> bool {
+    pred.mode == ParamMode::Immediate && (pred.value != 0) == (opcode == OpCode::TJmp)
+}
+
+/// Whether the line is `arb` by a constant `amount`.
+fn adjusts_base(line: &Line, amount: i64) -> bool {
+    matches!(
+        instruction(line),
+        Some((OpCode::AdjustRelativeBase, [Operand { mode: ParamMode::Immediate, value }]))
+            if *value == amount
+    )
+}
+
+fn call(block: &Block) -> Option<Call> {
+    let (last, rest) = block.lines.split_last()?;
+    let target = match instruction(last)? {
+        (opcode @ (OpCode::TJmp | OpCode::FJmp), [pred, target])
+            if always_taken(opcode, pred)
+                && target.mode == ParamMode::Immediate
+                && target.value >= 0 =>
+        {
+            target.value as usize
+        }
+        _ => return None,
+    };
+
+    let end = end_of(block) as i64;
+    let link = rest.iter().rposition(|line| {
+        let value = match instruction(line) {
+            Some((
+                opcode,
+                [a, b, Operand {
+                    mode: ParamMode::Relative,
+                    value: 0,
+                }],
+            )) if a.mode == ParamMode::Immediate && b.mode == ParamMode::Immediate => {
+                match opcode {
+                    OpCode::Add => a.value.checked_add(b.value),
+                    OpCode::Mul => a.value.checked_mul(b.value),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        value == Some(end)
+    })?;
+
+    // Count the arguments stored in the run of instructions before the call
+    let stored = rest
+        .iter()
+        .enumerate()
+        .rev()
+        .filter(|(i, _)| *i != link)
+        .map_while(|(_, line)| {
+            let (opcode, operands) = instruction(line)?;
+            match operands[opcode.dest_param()?] {
+                Operand {
+                    mode: ParamMode::Relative,
+                    value,
+                } if value > 0 => Some(value),
+                _ => None,
+            }
+        })
+        .collect::<BTreeSet<_>>();
+    let args = (1..).take_while(|n| stored.contains(n)).count() as i64;
+
+    Some(Call { target, args, link })
+}
+
+/// Turns the run of stores to `out1`, `out2` and so on at the end of `stmts`
+/// into the arguments of a call, if they don't depend on each other.
+fn call_stmt(stmts: &mut Vec<Stmt>, target: usize) -> Stmt {
+    let stores = stmts
+        .iter()
+        .rev()
+        .take_while(|stmt| matches!(stmt, Stmt::Assign(Expr::Var(Var::Out(n)), _) if *n > 0))
+        .count();
+    let start = stmts.len() - stores;
+
+    let mut args = BTreeMap::new();
+    for stmt in &stmts[start..] {
+        if let Stmt::Assign(Expr::Var(Var::Out(n)), value) = stmt {
+            let mut read = BTreeSet::new();
+            value.vars(&mut read);
+            let depends = read
+                .iter()
+                .any(|var| matches!(var, Var::Out(m) if args.contains_key(m)));
+            if depends || args.contains_key(n) {
+                return Stmt::Call(target, vec![]);
+            }
+            args.insert(*n, value.clone());
+        }
+    }
+
+    if !args.keys().copied().eq(1..=args.len() as i64) {
+        return Stmt::Call(target, vec![]);
+    }
+    stmts.truncate(start);
+    Stmt::Call(target, args.into_values().collect())
+}
+
+/// Translates a block of the function with the given frame.
+fn translate(block: &Block, frame: &Frame, entry: bool, call: Option<&Call>) -> (Vec<Stmt>, Term) {
+    let lines = &block.lines;
+    let end = end_of(block);
+    let mut stmts = Vec::new();
+    let mut skip = BTreeSet::new();
+
+    if entry && frame.size > 0 {
+        skip.insert(0);
+    }
+    if let Some(call) = call {
+        skip.insert(call.link);
+    }
+
+    // A return moves the base back to the caller's frame and jumps through
+    // the return address
+    let last = lines.last().and_then(instruction);
+    let returns = match last {
+        Some((opcode @ (OpCode::TJmp | OpCode::FJmp), [pred, target])) => {
+            always_taken(opcode, pred)
+                && *target
+                    == Operand {
+                        mode: ParamMode::Relative,
+                        value: 0,
+                    }
+                && (frame.size == 0
+                    || lines.len() >= 2 && adjusts_base(&lines[lines.len() - 2], -frame.size))
+        }
+        _ => false,
+    };
+    if returns && frame.size > 0 {
+        skip.insert(lines.len() - 2);
+    }
+
+    // The operands of the block's instructions, which earlier ones may patch
+    let operand_words = lines
+        .iter()
+        .map(|line| {
+            (
+                line.address + 1..line.address + line.item.len(),
+                line.address,
+            )
+        })
+        .flat_map(|(words, address)| words.map(move |word| (word, address)))
+        .collect::<HashMap<_, _>>();
+    let mut patches = HashMap::new();
+
+    let operand = |patches: &HashMap<usize, Expr>, line: &Line, n: usize, operand: Operand| match (
+        patches.get(&(line.address + n + 1)),
+        operand.mode,
+    ) {
+        (Some(e), ParamMode::Immediate) => e.clone(),
+        (Some(e), ParamMode::Positional) => Expr::Mem(Box::new(e.clone())),
+        (Some(e), ParamMode::Relative) => Expr::Mem(Box::new(add(Expr::Base, e.clone()))),
+        (None, ParamMode::Immediate) => Expr::Const(operand.value),
+        // Reading back a patch that has yet to be used
+        (None, ParamMode::Positional) if operand.value >= 0 => patches
+            .get(&(operand.value as usize))
+            .cloned()
+            .unwrap_or(Expr::Var(Var::Global(operand.value as usize))),
+        (None, ParamMode::Positional) => Expr::Mem(Box::new(Expr::Const(operand.value))),
+        (None, ParamMode::Relative) => Expr::Var(frame.var(operand.value)),
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        let (opcode, operands) = match instruction(line) {
+            Some(instruction) if !skip.contains(&i) => instruction,
+            _ => continue,
+        };
+        let read = |patches: &HashMap<_, _>, n| operand(patches, line, n, operands[n]);
+
+        let value = match opcode {
+            OpCode::Add => add(read(&patches, 0), read(&patches, 1)),
+            OpCode::Mul => mul(read(&patches, 0), read(&patches, 1)),
+            OpCode::Lt => compare(Op::Lt, read(&patches, 0), read(&patches, 1)),
+            OpCode::Eq => compare(Op::Eq, read(&patches, 0), read(&patches, 1)),
+            OpCode::Input => {
+                stmts.push(Stmt::Assign(read(&patches, 0), Expr::Input));
+                continue;
+            }
+            OpCode::Output => {
+                stmts.push(Stmt::Output(read(&patches, 0)));
+                continue;
+            }
+            OpCode::AdjustRelativeBase => {
+                stmts.push(Stmt::AdjustBase(read(&patches, 0)));
+                continue;
+            }
+            OpCode::Halt => {
+                stmts.push(Stmt::Halt);
+                continue;
+            }
+            OpCode::TJmp | OpCode::FJmp => continue,
+        };
+
+        // Storing into an operand of a later instruction patches it
+        let dest = operands[2];
+        let dest_address = line.address + 3;
+        if dest.mode == ParamMode::Positional && !patches.contains_key(&dest_address) {
+            if let Some(owner) = usize::try_from(dest.value)
+                .ok()
+                .and_then(|word| operand_words.get(&word))
+            {
+                if *owner > line.address {
+                    patches.insert(dest.value as usize, value);
+                    continue;
+                }
+            }
+        }
+        stmts.push(Stmt::Assign(read(&patches, 2), value));
+    }
+
+    let term = match (block.end, last) {
+        (BlockEnd::Jump, Some((opcode, [pred, target])))
+        | (BlockEnd::IndirectJump, Some((opcode, [pred, target]))) => {
+            let line = lines.last().unwrap();
+            let cond = match pred.mode {
+                ParamMode::Immediate => None,
+                _ => {
+                    // Fold in a comparison that was stored in the flag
+                    let flag = operand(&patches, line, 0, *pred);
+                    let cond = match stmts.pop() {
+                        Some(Stmt::Assign(dest, value @ Expr::Binary(Op::Lt | Op::Eq, ..)))
+                            if dest == flag =>
+                        {
+                            value
+                        }
+                        Some(stmt) => {
+                            stmts.push(stmt);
+                            flag
+                        }
+                        None => flag,
+                    };
+                    Some(if opcode == OpCode::TJmp {
+                        cond
+                    } else {
+                        not(cond)
+                    })
+                }
+            };
+
+            if cond.is_none() && !always_taken(opcode, pred) {
+                Term::Fall(end)
+            } else if let Some(call) = call {
+                let stmt = call_stmt(&mut stmts, call.target);
+                stmts.push(stmt);
+                Term::Fall(end)
+            } else {
+                match (cond, operand(&patches, line, 1, *target)) {
+                    (Some(cond), Expr::Const(target)) if target >= 0 => {
+                        Term::Branch(cond, target as usize, end)
+                    }
+                    (None, Expr::Const(target)) if target >= 0 => Term::Goto(target as usize),
+                    (cond, target) => {
+                        let stmt = if returns {
+                            Stmt::Return
+                        } else {
+                            Stmt::GotoIndirect(target)
+                        };
+                        match cond {
+                            Some(cond) => {
+                                stmts.push(Stmt::If(cond, vec![stmt]));
+                                Term::Fall(end)
+                            }
+                            None => {
+                                stmts.push(stmt);
+                                Term::End
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        (BlockEnd::Next, _) => Term::Fall(end),
+        (BlockEnd::Invalid(address), _) => {
+            stmts.push(Stmt::Invalid(address));
+            Term::End
+        }
+        _ => Term::End,
+    };
+
+    (stmts, term)
+}
+
+/// The blocks of a function, in address order.
+struct Function {
+    entry: usize,
+    frame: Frame,
+    starts: Vec<usize>,
+    blocks: Vec<(Vec<Stmt>, Term)>,
+    preds: Vec<BTreeSet<usize>>,
+}
+
+impl Function {
+    fn new(cfg: &Cfg, calls: &BTreeMap<usize, Call>, entry: usize) -> Self {
+        let size = match cfg.blocks.get(&entry).and_then(|block| block.lines.first()) {
+            Some(line) => match instruction(line) {
+                Some((
+                    OpCode::AdjustRelativeBase,
+                    [Operand {
+                        mode: ParamMode::Immediate,
+                        value,
+                    }],
+                )) if *value > 0 => *value,
+                _ => 0,
+            },
+            None => 0,
+        };
+        let args = calls
+            .values()
+            .filter(|call| call.target == entry)
+            .map(|call| call.args)
+            .max()
+            .unwrap_or(0);
+        let frame = Frame { size, args };
+
+        let mut translated = BTreeMap::new();
+        let mut work = vec![entry];
+        while let Some(start) = work.pop() {
+            if translated.contains_key(&start) {
+                continue;
+            }
+            if let Some(block) = cfg.blocks.get(&start) {
+                let (stmts, term) = translate(block, &frame, start == entry, calls.get(&start));
+                work.extend(term.successors());
+                translated.insert(start, (stmts, term));
+            }
+        }
+
+        let starts = translated.keys().copied().collect::<Vec<_>>();
+        let index = |address: &usize| starts.binary_search(address).ok();
+        let mut preds = vec![BTreeSet::new(); starts.len()];
+        for (i, (_, term)) in translated.values().enumerate() {
+            for successor in term.successors().iter().filter_map(index) {
+                preds[successor].insert(i);
+            }
+        }
+
+        Function {
+            entry,
+            frame,
+            blocks: translated.into_values().collect(),
+            starts,
+            preds,
+        }
+    }
+
+    /// Whether the blocks `first..=last` can only be entered at the top.
+    fn single_entry(&self, first: usize, last: usize) -> bool {
+        (first + 1..=last).all(|i| {
+            self.preds[i]
+                .iter()
+                .all(|pred| (first..=last).contains(pred))
+        })
+    }
+
+    /// Where execution carries on after block `i`, with a `goto` if that isn't
+    /// the block after it.
+    fn fall(&self, i: usize, stmts: &mut Vec<Stmt>) {
+        if let Term::Fall(next) | Term::Branch(_, _, next) = self.blocks[i].1 {
+            if self.starts.get(i + 1) != Some(&next) {
+                stmts.push(Stmt::Goto(next));
+            }
+        }
+    }
+
+    /// Structures the blocks in `first..last`.
+    fn structure(&self, first: usize, last: usize) -> Vec<Stmt> {
+        let mut stmts = Vec::new();
+        let mut i = first;
+
+        while i < last {
+            // A jump back to the top of single-entry blocks is a loop
+            let tail = (i..last).rev().find(|j| {
+                matches!(self.blocks[*j].1, Term::Branch(_, target, _) if target == self.starts[i])
+                    && self.single_entry(i, *j)
+            });
+            if let Some(j) = tail {
+                let (block, term) = &self.blocks[j];
+                let mut body = self.structure(i, j);
+                body.push(Stmt::Label(self.starts[j]));
+                body.extend(block.iter().cloned());
+                if let Term::Branch(cond, ..) = term {
+                    stmts.push(Stmt::DoWhile(body, cond.clone()));
+                }
+                self.fall(j, &mut stmts);
+                i = j + 1;
+                continue;
+            }
+
+            let (block, term) = &self.blocks[i];
+            stmts.push(Stmt::Label(self.starts[i]));
+            stmts.extend(block.iter().cloned());
+
+            match term {
+                Term::Branch(cond, target, next) => {
+                    // A jump forward over single-entry blocks is an `if`
+                    let skipped = self.starts.binary_search(target).ok().filter(|k| {
+                        i + 1 < *k
+                            && *k <= last
+                            && self.starts[i + 1] == *next
+                            && self.single_entry(i, k - 1)
+                    });
+                    if let Some(k) = skipped {
+                        stmts.push(Stmt::If(not(cond.clone()), self.structure(i + 1, k)));
+                        i = k;
+                        continue;
+                    }
+
+                    if target != next {
+                        stmts.push(Stmt::If(cond.clone(), vec![Stmt::Goto(*target)]));
+                    }
+                    self.fall(i, &mut stmts);
+                }
+                Term::Goto(target) => {
+                    if self.starts.get(i + 1) != Some(target) {
+                        stmts.push(Stmt::Goto(*target));
+                    }
+                }
+                Term::Fall(_) => self.fall(i, &mut stmts),
+                Term::End => {}
+            }
+            i += 1;
+        }
+
+        stmts
+    }
+
+    fn write(&self, out: &mut String) {
+        let body = self.structure(0, self.blocks.len());
+
+        let params = (1..=self.frame.args)
+            .map(|n| format!("int {}", Var::Arg(n)))
+            .collect::<Vec<_>>();
+        writeln!(
+            out,
+            "void {}({}) {{",
+            function_name(self.entry),
+            params.join(", ")
+        )
+        .unwrap();
+
+        let mut vars = BTreeSet::new();
+        body.iter().for_each(|stmt| stmt.vars(&mut vars));
+        let locals = vars
+            .iter()
+            .filter(|var| matches!(var, Var::Local(_) | Var::Out(_)))
+            .map(Var::to_string)
+            .collect::<Vec<_>>();
+        if !locals.is_empty() {
+            writeln!(out, "    int {};\n", locals.join(", ")).unwrap();
+        }
+
+        let mut labels = BTreeSet::new();
+        body.iter().for_each(|stmt| stmt.gotos(&mut labels));
+        write_stmts(out, &body, 1, &labels);
+
+        writeln!(out, "}}").unwrap();
+    }
+}
+
+fn write_stmts(out: &mut String, stmts: &[Stmt], depth: usize, labels: &BTreeSet<usize>) {
+    let indent = "    ".repeat(depth);
+
+    for stmt in stmts {
+        match stmt {
+            Stmt::Label(address) if labels.contains(address) => {
+                writeln!(out, "{}l{}:", "    ".repeat(depth - 1), address).unwrap()
+            }
+            Stmt::Label(_) => {}
+            Stmt::If(cond, body) => match body.as_slice() {
+                [single] if single.simple().is_some() => {
+                    writeln!(out, "{}if ({}) {}", indent, cond, single.simple().unwrap()).unwrap()
+                }
+                _ => {
+                    writeln!(out, "{}if ({}) {{", indent, cond).unwrap();
+                    write_stmts(out, body, depth + 1, labels);
+                    writeln!(out, "{}}}", indent).unwrap();
+                }
+            },
+            Stmt::DoWhile(body, cond) => {
+                writeln!(out, "{}do {{", indent).unwrap();
+                write_stmts(out, body, depth + 1, labels);
+                writeln!(out, "{}}} while ({});", indent, cond).unwrap();
+            }
+            stmt => writeln!(out, "{}{}", indent, stmt.simple().unwrap()).unwrap(),
+        }
+    }
+}
+
+/// Decompiles `code`, starting from address 0 and following every call it
+/// makes.
+pub fn decompile(code: &[i64]) -> String {
+    // Calls are jumps that come back, so the code after each one is only
+    // found once the call is recognised
+    let mut entries = BTreeSet::from([0]);
+    let (cfg, calls) = loop {
+        let cfg = Cfg::with_entries(code, entries.iter().copied());
+        let calls = cfg
+            .blocks
+            .values()
+            .filter_map(|block| call(block).map(|call| (block.start, call)))
+            .collect::<BTreeMap<_, _>>();
+
+        let known = entries.len();
+        for (start, call) in &calls {
+            entries.insert(call.target);
+            entries.insert(end_of(&cfg.blocks[start]));
+        }
+        if entries.len() == known {
+            break (cfg, calls);
+        }
+    };
+
+    let functions = std::iter::once(0)
+        .chain(calls.values().map(|call| call.target))
+        .collect::<BTreeSet<_>>();
+
+    let mut out = String::new();
+    for (i, entry) in functions.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        Function::new(&cfg, &calls, *entry).write(&mut out);
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asm::assemble;
+
+    #[test]
+    fn test_expressions() {
+        let var = |address| Expr::Var(Var::Global(address));
+
+        assert_eq!(add(Expr::Const(2), var(5)).to_string(), "g5 + 2");
+        assert_eq!(add(var(5), Expr::Const(-1)).to_string(), "g5 - 1");
+        assert_eq!(add(Expr::Const(0), Expr::Const(161)), Expr::Const(161));
+        assert_eq!(mul(Expr::Const(1), var(5)), var(5));
+        assert_eq!(
+            mul(add(var(1), var(2)), Expr::Const(-3)).to_string(),
+            "(g1 + g2) * -3"
+        );
+        assert_eq!(neg(add(var(1), var(2))).to_string(), "-(g1 + g2)");
+        assert_eq!(
+            add(var(1), binary(Op::Sub, var(2), var(3))).to_string(),
+            "g1 + (g2 - g3)"
+        );
+        assert_eq!(
+            not(compare(Op::Lt, var(1), Expr::Const(10))).to_string(),
+            "g1 >= 10"
+        );
+        assert_eq!(not(not(var(1))), var(1));
+        assert_eq!(not(add(var(1), var(2))).to_string(), "!(g1 + g2)");
+    }
+
+    #[test]
+    fn test_loop() {
+        let code = assemble(
+            "
+                    in [counter]
+            loop:   out [counter]
+                    add [counter], #-1, [counter]
+                    eq [counter], #0, [flag]
+                    jf [flag], #loop
+                    hlt
+            counter: data 0
+            flag:   data 0
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            decompile(&code),
+            concat!(
+                "void main() {\n",
+                "    g16 = input();\n",
+                "    do {\n",
+                "        output(g16);\n",
+                "        g16 -= 1;\n",
+                "    } while (g16 != 0);\n",
+                "    halt();\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_calls() {
+        let code = assemble(
+            "
+                    arb #100
+                    in [r+1]
+                    add #ret, #0, [r+0]
+                    jt #1, #double
+            ret:    out [r+1]
+                    hlt
+            double: arb #2
+                    mul [r-1], #2, [r-1]
+                    arb #-2
+                    jt #1, [r+0]
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            decompile(&code),
+            concat!(
+                "void main() {\n",
+                "    int out1;\n",
+                "\n",
+                "    f14(input());\n",
+                "    output(out1);\n",
+                "    halt();\n",
+                "}\n",
+                "\n",
+                "void f14(int arg1) {\n",
+                "    arg1 *= 2;\n",
+                "    return;\n",
+                "}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_if_and_patching() {
+        let code = assemble(
+            "
+                    in [x]
+                    lt [x], #10, [flag]
+                    jt [flag], #small
+                    out #1
+            small:  add [x], #table, [ptr+1]
+            ptr:    out [0]
+                    jt #1, [x]
+            x:      data 0
+            flag:   data 0
+            table:  data 7, 8, 9
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            decompile(&code),
+            concat!(
+                "void main() {\n",
+                "    g20 = input();\n",
+                "    if (g20 >= 10) {\n",
+                "        output(1);\n",
+                "    }\n",
+                "    output(mem[g20 + 22]);\n",
+                "    goto *g20;\n",
+                "}\n",
+            )
+        );
+
+        // Builds up an address in the operand, then stores through it
+        let code = assemble(
+            "
+                    add [x], [x], [dest+3]
+                    add [dest+3], #1, [dest+3]
+            dest:   add #7, #0, [0]
+                    hlt
+            x:      data 0
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            decompile(&code),
+            "void main() {\n    mem[g13 + g13 + 1] = 7;\n    halt();\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_gotos() {
+        // Jumps into the middle of a loop, so it can't be structured
+        let code = assemble(
+            "
+                    in [x]
+                    jt [x], #middle
+            top:    out #1
+            middle: out #2
+                    add [x], #-1, [x]
+                    jt [x], #top
+                    hlt
+            x:      data 0
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(
+            decompile(&code),
+            concat!(
+                "void main() {\n",
+                "    g17 = input();\n",
+                "    if (g17) goto l7;\n",
+                "l5:\n",
+                "    output(1);\n",
+                "l7:\n",
+                "    output(2);\n",
+                "    g17 -= 1;\n",
+                "    if (g17) goto l5;\n",
+                "    halt();\n",
+                "}\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,738 @@
+//! Differential testing of the ways the VM can be run.
+//!
+//! Each day used to have its own copy of the VM, and the copies drifted
+//! apart: day 5's returned `code[0]` when the program halted, day 7 part 1
+//! drained a queue of inputs, day 7 part 2 resumed by hand and kept the last
+//! output, and day 9 onwards added relative mode. They have since been
+//! replaced by this crate's VM, which can be driven in each of those styles,
+//! on either backend, with any word type and in any arithmetic mode. The
+//! harness generates random programs limited to the instructions of day 5 or
+//! of day 9, many of which modify themselves or go wrong somehow, runs them
+//! every way and compares the outputs, final memory and errors with those of
+//! a reference interpreter.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::iter;
+
+use crate::arithmetic::Arithmetic;
+use crate::error::{Fault, IntcodeError};
+use crate::io::QueueIo;
+use crate::opcode::{OpCode, ParamMode};
+use crate::rng::Rng;
+use crate::vm::{Backend, ExecutionStatus, VM};
+use crate::word::{BigInt, Word};
+
+/// The number of instructions each program may execute.
+const FUEL: u64 = 500;
+/// The number of words of data after the instructions.
+const DATA: usize = 12;
+/// The memory limit of the programs that have one.
+const MEMORY_LIMIT: usize = 1000;
+
+/// The instructions the generated programs are limited to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Subset {
+    /// Opcodes 1 to 8 and 99 in positional and immediate mode, as supported
+    /// by days 2, 5 and 7.
+    Day5,
+    /// Adds `arb` and relative mode, with memory past the end of the
+    /// program, as supported from day 9 onwards.
+    Day9,
+}
+
+/// A way of running the VM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Driver {
+    /// `run_with` and a `QueueIo`, draining a queue of input like day 7
+    /// part 1.
+    Queue,
+    /// Calling `resume` and giving it input when asked, like day 7 part 2.
+    Resume,
+    /// Calling `step` one instruction at a time.
+    Step,
+}
+
+const DRIVERS: [Driver; 3] = [Driver::Queue, Driver::Resume, Driver::Step];
+const BACKENDS: [Backend; 2] = [Backend::Interpreter, Backend::Compiled];
+const ARITHMETICS: [Arithmetic; 3] = [
+    Arithmetic::Wrapping,
+    Arithmetic::Checked,
+    Arithmetic::Saturating,
+];
+
+/// A random program and the input to run it with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub code: Vec<i64>,
+    pub inputs: Vec<i64>,
+    pub memory_limit: Option<usize>,
+}
+
+/// A number that is out of the ordinary as an operand: a negative address,
+/// one at the memory limit or too large to be an address at all, or big
+/// enough to overflow.
+fn wild(rng: &mut Rng) -> i64 {
+    match rng.range(0, 4) {
+        0 => rng.range(-100, -1),
+        1 => MEMORY_LIMIT as i64 + rng.range(-2, 2),
+        2 => 1 << rng.range(20, 62),
+        3 => i64::MAX - rng.range(0, 2),
+        _ => i64::MIN + rng.range(0, 2),
+    }
+}
+
+impl Program {
+    /// Generates a program from `seed` that uses only the instructions in
+    /// `subset`. Most of its operands refer to the data after its
+    /// instructions, some write over the instructions themselves, and a few
+    /// are wild: unknown opcodes and parameter modes, writes to immediate
+    /// operands, jumps into the middle of instructions and numbers that are
+    /// negative or too large for an address. About half of the programs get
+    /// a memory limit.
+    pub fn generate(subset: Subset, seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+
+        let mut opcodes = vec![
+            OpCode::Add,
+            OpCode::Mul,
+            OpCode::Input,
+            OpCode::Output,
+            OpCode::TJmp,
+            OpCode::FJmp,
+            OpCode::Lt,
+            OpCode::Eq,
+        ];
+        if subset == Subset::Day9 {
+            opcodes.push(OpCode::AdjustRelativeBase);
+        }
+
+        // Relative mode starts out pointing at the data. `None` is a word
+        // that isn't an instruction at all.
+        let count = rng.range(4, 24);
+        let program = iter::once(Some(OpCode::AdjustRelativeBase))
+            .filter(|_| subset == Subset::Day9)
+            .chain((0..count).map(|_| match rng.range(0, 39) {
+                0 => None,
+                1 | 2 => Some(OpCode::Halt),
+                _ => Some(rng.choose(&opcodes)),
+            }))
+            .chain(iter::once(Some(OpCode::Halt)))
+            .collect::<Vec<_>>();
+
+        let mut starts = Vec::new();
+        let mut data = 0;
+        for opcode in &program {
+            starts.push(data as i64);
+            data += opcode.map_or(1, |opcode| opcode.effect());
+        }
+        let data = data as i64;
+
+        // Immediate mode first, so destinations can skip it
+        let modes: &[ParamMode] = match subset {
+            Subset::Day5 => &[ParamMode::Immediate, ParamMode::Positional],
+            Subset::Day9 => &[
+                ParamMode::Immediate,
+                ParamMode::Positional,
+                ParamMode::Relative,
+            ],
+        };
+
+        let mut code = Vec::new();
+        for (i, opcode) in program.into_iter().enumerate() {
+            let opcode = match opcode {
+                Some(opcode) => opcode,
+                None => {
+                    code.push(rng.choose(&[0, 42, 98, -1, -99]));
+                    continue;
+                }
+            };
+            let mut instruction = opcode as i64;
+            let mut operands = Vec::new();
+
+            for n in 0..opcode.param_count() {
+                let jump = matches!(opcode, OpCode::TJmp | OpCode::FJmp) && n == 1;
+                let dest = opcode.dest_param() == Some(n);
+
+                let (mode, value) = match opcode {
+                    OpCode::AdjustRelativeBase if i == 0 => (ParamMode::Immediate, data),
+                    _ if rng.range(0, 29) == 0 => (rng.choose(modes), wild(&mut rng)),
+                    OpCode::AdjustRelativeBase => (ParamMode::Immediate, rng.range(-3, 3)),
+                    _ if jump => match rng.range(0, 7) {
+                        0 => (ParamMode::Immediate, rng.range(0, data + DATA as i64)),
+                        _ => (ParamMode::Immediate, rng.choose(&starts)),
+                    },
+                    // One in six positional and relative operands refers to
+                    // the instructions rather than the data
+                    _ => match rng.choose(&modes[usize::from(dest)..]) {
+                        ParamMode::Positional => match rng.range(0, 5) {
+                            0 => (ParamMode::Positional, rng.range(0, data - 1)),
+                            _ => (ParamMode::Positional, data + rng.range(0, DATA as i64 - 1)),
+                        },
+                        ParamMode::Immediate => (ParamMode::Immediate, rng.range(-10, 20)),
+                        ParamMode::Relative => match rng.range(0, 5) {
+                            0 => (ParamMode::Relative, rng.range(-data, -1)),
+                            _ => (ParamMode::Relative, rng.range(0, DATA as i64)),
+                        },
+                    },
+                };
+
+                // Rarely a digit that isn't a mode, or an immediate
+                // destination
+                let mode = match rng.range(0, 59) {
+                    0 => rng.range(3, 9),
+                    1 if dest => ParamMode::Immediate as i64,
+                    _ => mode as i64,
+                };
+
+                instruction += mode * 10_i64.pow(n as u32 + 2);
+                operands.push(value);
+            }
+
+            code.push(instruction);
+            code.extend(operands);
+        }
+
+        code.extend((0..DATA).map(|_| rng.range(-5, 20)));
+        let inputs = (0..rng.range(0, 4)).map(|_| rng.range(-5, 20)).collect();
+        let memory_limit = Some(MEMORY_LIMIT).filter(|_| rng.range(0, 1) == 0);
+
+        Program {
+            code,
+            inputs,
+            memory_limit,
+        }
+    }
+}
+
+/// How a program ran.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome<W = i64> {
+    pub outputs: Vec<W>,
+    /// The program as it was when it stopped.
+    pub code: Vec<W>,
+    /// The nonzero words past the end of the program.
+    pub extra_memory: BTreeMap<usize, W>,
+    /// The error it stopped with, if it didn't halt.
+    pub error: Option<IntcodeError<W>>,
+}
+
+fn join<W: Word>(words: &[W]) -> String {
+    words.iter().map(W::to_string).collect::<Vec<_>>().join(",")
+}
+
+impl<W: Word> fmt::Display for Outcome<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "outputs: {}", join(&self.outputs))?;
+        writeln!(f, "code: {}", join(&self.code))?;
+        write!(f, "extra memory:")?;
+        for (address, word) in &self.extra_memory {
+            write!(f, " {}={}", address, word)?;
+        }
+        writeln!(f)?;
+        match &self.error {
+            Some(e) => write!(f, "error: {}", e),
+            None => write!(f, "halted"),
+        }
+    }
+}
+
+/// A plain interpreter to compare the VM with, written from the puzzle
+/// descriptions and the errors `IntcodeError` describes rather than from the
+/// VM's code.
+struct Reference<W> {
+    code: Vec<W>,
+    /// Everything written past the end of the program.
+    extra_memory: BTreeMap<usize, W>,
+    memory_limit: Option<usize>,
+    arithmetic: Arithmetic,
+    ip: usize,
+    relative_base: W,
+    /// The parameter modes of the instruction at `ip`, as decoded.
+    modes: Vec<i64>,
+}
+
+impl<W: Word> Reference<W> {
+    fn get(&self, address: usize) -> W {
+        match self.code.get(address) {
+            Some(word) => word.clone(),
+            None => self.extra_memory.get(&address).cloned().unwrap_or_default(),
+        }
+    }
+
+    fn set(&mut self, address: usize, word: W) {
+        match self.code.get_mut(address) {
+            Some(slot) => *slot = word,
+            None => {
+                self.extra_memory.insert(address, word);
+            }
+        }
+    }
+
+    fn fault(&self) -> Fault<W> {
+        Fault {
+            ip: self.ip,
+            instruction: self.get(self.ip),
+            relative_base: self.relative_base.clone(),
+        }
+    }
+
+    fn arith(&self, word: Option<W>) -> Result<W, IntcodeError<W>> {
+        word.ok_or_else(|| IntcodeError::Overflow {
+            fault: self.fault(),
+        })
+    }
+
+    /// Decodes the instruction at `ip` into its opcode and `modes`.
+    fn decode(&mut self) -> Result<i64, IntcodeError<W>> {
+        let word = self.get(self.ip);
+        let unknown = |opcode| IntcodeError::UnknownOpcode {
+            fault: self.fault(),
+            opcode,
+        };
+
+        let word = match word.to_i64() {
+            Some(word) => word,
+            // Too large to decode, so reported by its last two digits
+            None => {
+                let digits = word.to_string();
+                let low: i64 = digits[digits.len() - 2..].parse().unwrap();
+                return Err(unknown(if word.is_negative() { -low } else { low }));
+            }
+        };
+
+        let opcode = word % 100;
+        if !matches!(opcode, 1..=9 | 99) {
+            return Err(unknown(opcode));
+        }
+
+        // Every digit above the opcode must be a mode, even past the last
+        // parameter
+        self.modes.clear();
+        let mut rest = word / 100;
+        while rest > 0 {
+            let mode = rest % 10;
+            if mode > 2 {
+                return Err(IntcodeError::InvalidParamMode {
+                    fault: self.fault(),
+                    param: self.modes.len(),
+                    mode,
+                });
+            }
+            self.modes.push(mode);
+            rest /= 10;
+        }
+
+        Ok(opcode)
+    }
+
+    fn mode(&self, n: usize) -> i64 {
+        self.modes.get(n).copied().unwrap_or(0)
+    }
+
+    /// The address parameter `n` refers to, in positional or relative mode.
+    fn address(&self, n: usize) -> Result<usize, IntcodeError<W>> {
+        let operand = self.get(self.ip + n + 1);
+        let address = match self.mode(n) {
+            2 => self.arith(operand.add(&self.relative_base, self.arithmetic))?,
+            _ => operand,
+        };
+
+        if address.is_negative() {
+            return Err(IntcodeError::NegativeAddress {
+                fault: self.fault(),
+                param: n,
+                address,
+            });
+        }
+        match address.to_i64() {
+            Some(a) if self.memory_limit.is_none_or(|limit| (a as usize) < limit) => Ok(a as usize),
+            _ => Err(IntcodeError::AddressOutOfRange {
+                fault: self.fault(),
+                param: n,
+                address,
+                limit: self.memory_limit.unwrap_or(usize::MAX),
+            }),
+        }
+    }
+
+    fn read(&self, n: usize) -> Result<W, IntcodeError<W>> {
+        match self.mode(n) {
+            1 => Ok(self.get(self.ip + n + 1)),
+            _ => Ok(self.get(self.address(n)?)),
+        }
+    }
+
+    /// The address parameter `n` writes to. This is worked out before the
+    /// result is, so a bad destination is reported rather than an overflow.
+    fn dest(&self, n: usize) -> Result<usize, IntcodeError<W>> {
+        match self.mode(n) {
+            1 => Err(IntcodeError::ImmediateWrite {
+                fault: self.fault(),
+                param: n,
+            }),
+            _ => self.address(n),
+        }
+    }
+
+    /// Executes the instruction at `ip`, returning whether to carry on.
+    fn execute(
+        &mut self,
+        inputs: &mut impl Iterator<Item = W>,
+        outputs: &mut Vec<W>,
+    ) -> Result<bool, IntcodeError<W>> {
+        if self.ip >= self.code.len() {
+            return Err(IntcodeError::RanOffEnd {
+                fault: self.fault(),
+            });
+        }
+
+        let bool_word = |b: bool| W::from_i64(b as i64);
+        let opcode = self.decode()?;
+
+        let next = match opcode {
+            1 | 2 => {
+                let (a, b) = (self.read(0)?, self.read(1)?);
+                let dest = self.dest(2)?;
+                let result = match opcode {
+                    1 => a.add(&b, self.arithmetic),
+                    _ => a.mul(&b, self.arithmetic),
+                };
+                let result = self.arith(result)?;
+                self.set(dest, result);
+                self.ip + 4
+            }
+            3 => {
+                let input = inputs.next().ok_or_else(|| IntcodeError::InputExhausted {
+                    fault: self.fault(),
+                })?;
+                let dest = self.dest(0)?;
+                self.set(dest, input);
+                self.ip + 2
+            }
+            4 => {
+                outputs.push(self.read(0)?);
+                self.ip + 2
+            }
+            5 | 6 => {
+                let pred = self.read(0)?;
+                let target = self.read(1)?;
+                if pred.is_zero() == (opcode == 6) {
+                    if target.is_negative() {
+                        return Err(IntcodeError::NegativeJump {
+                            fault: self.fault(),
+                            target,
+                        });
+                    }
+                    // Too large to be an address, so certainly past the end
+                    target.to_i64().map_or(usize::MAX, |target| target as usize)
+                } else {
+                    self.ip + 3
+                }
+            }
+            7 => {
+                let less = self.read(0)? < self.read(1)?;
+                let dest = self.dest(2)?;
+                self.set(dest, bool_word(less));
+                self.ip + 4
+            }
+            8 => {
+                let equal = self.read(0)? == self.read(1)?;
+                let dest = self.dest(2)?;
+                self.set(dest, bool_word(equal));
+                self.ip + 4
+            }
+            9 => {
+                let base = self.relative_base.add(&self.read(0)?, self.arithmetic);
+                self.relative_base = self.arith(base)?;
+                self.ip + 2
+            }
+            _ => return Ok(false),
+        };
+
+        self.ip = next;
+        Ok(true)
+    }
+
+    fn run(program: &Program, arithmetic: Arithmetic) -> Outcome<W> {
+        let mut vm = Reference {
+            code: program.code.iter().map(|n| W::from_i64(*n)).collect(),
+            extra_memory: BTreeMap::new(),
+            memory_limit: program.memory_limit,
+            arithmetic,
+            ip: 0,
+            relative_base: W::default(),
+            modes: Vec::new(),
+        };
+        let mut inputs = program.inputs.iter().map(|n| W::from_i64(*n));
+        let mut outputs = Vec::new();
+
+        let mut fuel = FUEL;
+        let error = loop {
+            if fuel == 0 {
+                break Some(IntcodeError::OutOfFuel { fault: vm.fault() });
+            }
+            match vm.execute(&mut inputs, &mut outputs) {
+                Ok(true) => fuel -= 1,
+                Ok(false) => break None,
+                Err(e) => break Some(e),
+            }
+        };
+
+        Outcome {
+            outputs,
+            code: vm.code,
+            extra_memory: vm
+                .extra_memory
+                .into_iter()
+                .filter(|(_, word)| !word.is_zero())
+                .collect(),
+            error,
+        }
+    }
+}
+
+/// Runs `program` on the VM in the way given.
+pub fn run<W: Word>(
+    program: &Program,
+    arithmetic: Arithmetic,
+    driver: Driver,
+    backend: Backend,
+) -> Outcome<W> {
+    let mut vm = VM::from_words(program.code.iter().map(|n| W::from_i64(*n)).collect());
+    vm.set_backend(backend);
+    vm.set_arithmetic(arithmetic);
+    vm.set_memory_limit(program.memory_limit);
+    vm.set_fuel(Some(FUEL));
+    let mut inputs = program
+        .inputs
+        .iter()
+        .map(|n| W::from_i64(*n))
+        .collect::<VecDeque<_>>();
+
+    let mut outputs = Vec::new();
+    let mut input = None;
+
+    // What to do when `resume` or `step` stops, or `None` to carry on
+    let mut stopped = |vm: &VM<W>, input: &mut Option<W>, status| match status {
+        ExecutionStatus::Output(n) => {
+            outputs.push(n);
+            None
+        }
+        ExecutionStatus::AwaitingInput => match inputs.pop_front() {
+            Some(n) => {
+                *input = Some(n);
+                None
+            }
+            None => Some(Some(IntcodeError::InputExhausted { fault: vm.fault() })),
+        },
+        ExecutionStatus::Complete => Some(None),
+        ExecutionStatus::OutOfFuel => Some(Some(IntcodeError::OutOfFuel { fault: vm.fault() })),
+        ExecutionStatus::Watchpoint(_) => None,
+    };
+
+    let error = match driver {
+        Driver::Queue => {
+            let mut io = QueueIo::new(inputs.clone());
+            let error = vm.run_with(&mut io).err();
+            outputs = io.output;
+            error
+        }
+        Driver::Resume => loop {
+            let status = match vm.resume(input.take()) {
+                Ok(status) => status,
+                Err(e) => break Some(e),
+            };
+            if let Some(error) = stopped(&vm, &mut input, status) {
+                break error;
+            }
+        },
+        Driver::Step => loop {
+            let status = match vm.step(&mut input) {
+                Ok(Some(status)) => status,
+                Ok(None) => continue,
+                Err(e) => break Some(e),
+            };
+            if let Some(error) = stopped(&vm, &mut input, status) {
+                break error;
+            }
+        },
+    };
+
+    Outcome {
+        outputs,
+        code: vm.code().to_vec(),
+        extra_memory: vm.extra_memory(),
+        error,
+    }
+}
+
+/// A way of running a program that didn't match the reference interpreter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub subset: Subset,
+    pub seed: u64,
+    pub program: Program,
+    pub arithmetic: Arithmetic,
+    pub driver: Driver,
+    pub backend: Backend,
+    pub word: &'static str,
+    /// What the reference interpreter did.
+    pub expected: String,
+    pub actual: String,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{:?} program {}, run by {:?} on {:?} with {} words and {:?} arithmetic",
+            self.subset, self.seed, self.driver, self.backend, self.word, self.arithmetic
+        )?;
+        writeln!(f, "program: {}", join(&self.program.code))?;
+        writeln!(f, "inputs: {}", join(&self.program.inputs))?;
+        if let Some(limit) = self.program.memory_limit {
+            writeln!(f, "memory limit: {}", limit)?;
+        }
+        writeln!(f, "expected:\n{}", self.expected)?;
+        write!(f, "actual:\n{}", self.actual)
+    }
+}
+
+fn check_words<W: Word>(
+    subset: Subset,
+    seed: u64,
+    program: &Program,
+    word: &'static str,
+    divergences: &mut Vec<Divergence>,
+) {
+    for arithmetic in ARITHMETICS {
+        let expected = Reference::<W>::run(program, arithmetic);
+
+        for driver in DRIVERS {
+            for backend in BACKENDS {
+                let actual = run::<W>(program, arithmetic, driver, backend);
+                if actual != expected {
+                    divergences.push(Divergence {
+                        subset,
+                        seed,
+                        program: program.clone(),
+                        arithmetic,
+                        driver,
+                        backend,
+                        word,
+                        expected: expected.to_string(),
+                        actual: actual.to_string(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+/// Runs the program generated from `seed` every way and returns the ways
+/// that didn't match the reference interpreter.
+pub fn check(subset: Subset, seed: u64) -> Vec<Divergence> {
+    let program = Program::generate(subset, seed);
+    let mut divergences = Vec::new();
+
+    check_words::<i64>(subset, seed, &program, "i64", &mut divergences);
+    check_words::<i128>(subset, seed, &program, "i128", &mut divergences);
+
+    // A loop that keeps squaring a number would never finish in its fuel
+    // with big integers, so only use them where i128 is enough
+    let wide = Reference::<i128>::run(&program, Arithmetic::Checked);
+    if !matches!(wide.error, Some(IntcodeError::Overflow { .. })) {
+        check_words::<BigInt>(subset, seed, &program, "BigInt", &mut divergences);
+    }
+
+    divergences
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_subsets() {
+        for seed in 0..50 {
+            let program = Program::generate(Subset::Day5, seed);
+            let instructions = &program.code[..program.code.len() - DATA];
+
+            // Walk the instructions as generated, stepping over words that
+            // aren't one
+            let mut ip = 0;
+            while ip < instructions.len() {
+                let word = instructions[ip];
+                match OpCode::try_from(word % 100) {
+                    Ok(opcode) => {
+                        assert_ne!(opcode, OpCode::AdjustRelativeBase);
+                        assert!(!(word / 100).to_string().contains('2'), "{}", word);
+                        ip += opcode.effect();
+                    }
+                    Err(_) => ip += 1,
+                }
+            }
+        }
+
+        assert_eq!(
+            Program::generate(Subset::Day9, 7),
+            Program::generate(Subset::Day9, 7)
+        );
+    }
+
+    #[test]
+    fn test_reference() {
+        let run = |code: Vec<i64>, inputs, arithmetic| {
+            let program = Program {
+                code,
+                inputs,
+                memory_limit: None,
+            };
+            Reference::<i64>::run(&program, arithmetic)
+        };
+
+        let quine = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let outcome = run(quine.clone(), vec![], Arithmetic::Checked);
+        assert_eq!(outcome.outputs, quine);
+        assert_eq!(outcome.error, None);
+        assert_eq!(outcome.extra_memory.get(&100), Some(&16));
+
+        // Squares its input until it overflows
+        let squares = vec![3, 9, 2, 9, 9, 9, 1105, 1, 2, 0];
+        let error = run(squares.clone(), vec![3], Arithmetic::Checked).error;
+        assert_eq!(
+            error.unwrap().to_string(),
+            "Arithmetic overflow at ip 2 (instruction 2, relative base 0)"
+        );
+        let outcome = run(squares, vec![3], Arithmetic::Saturating);
+        assert_eq!(outcome.code[9], i64::MAX);
+        assert!(matches!(
+            outcome.error,
+            Some(IntcodeError::OutOfFuel { .. })
+        ));
+
+        // Writes an unknown opcode over its next instruction
+        let error = run(vec![1101, 0, 42, 4, 0], vec![], Arithmetic::Wrapping).error;
+        assert_eq!(
+            error.unwrap().to_string(),
+            "Unknown opcode 42 at ip 4 (instruction 42, relative base 0)"
+        );
+
+        let error = run(vec![1106, 0, -5], vec![], Arithmetic::Wrapping).error;
+        assert!(matches!(error, Some(IntcodeError::NegativeJump { .. })));
+    }
+
+    #[test]
+    fn test_no_divergences() {
+        for subset in [Subset::Day5, Subset::Day9] {
+            for seed in 0..100 {
+                let divergences = check(subset, seed);
+                assert!(divergences.is_empty(), "{}", divergences[0]);
+            }
+        }
+    }
+}
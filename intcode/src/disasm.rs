@@ -0,0 +1,205 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::opcode::{digits, OpCode, ParamMode};
+
+/// The most words printed on a single `data` line.
+const DATA_PER_LINE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Operand {
+    pub mode: ParamMode,
+    pub value: i64,
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.mode {
+            ParamMode::Positional => write!(f, "[{}]", self.value),
+            ParamMode::Immediate => write!(f, "#{}", self.value),
+            ParamMode::Relative => write!(f, "[r{:+}]", self.value),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Instruction {
+        opcode: OpCode,
+        operands: Vec<Operand>,
+    },
+    /// Words that could not be decoded as an instruction.
+    Data(Vec<i64>),
+}
+
+impl Item {
+    /// The number of words the item occupies.
+    pub fn len(&self) -> usize {
+        match self {
+            Item::Instruction { opcode, .. } => opcode.effect(),
+            Item::Data(words) => words.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (name, operands) = match self {
+            Item::Instruction { opcode, operands } => (
+                opcode.mnemonic(),
+                operands.iter().map(Operand::to_string).collect::<Vec<_>>(),
+            ),
+            Item::Data(words) => ("data", words.iter().map(i64::to_string).collect()),
+        };
+
+        if operands.is_empty() {
+            write!(f, "{}", name)
+        } else {
+            write!(f, "{} {}", name, operands.join(", "))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Line {
+    pub address: usize,
+    pub item: Item,
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}: {}", self.address, self.item)
+    }
+}
+
+/// Decodes the instruction at the start of `code`.
+///
+/// Returns `None` if the VM would refuse to execute it, if it is cut off by
+/// the end of `code`, or if it has more parameter mode digits than the opcode
+/// has parameters (which the listing would not be able to reproduce).
+pub fn decode(code: &[i64]) -> Option<Item> {
+    let raw_instruction = *code.first()?;
+    let opcode = OpCode::try_from(raw_instruction % 100).ok()?;
+    let modes = digits(raw_instruction / 100);
+
+    if modes.len() > opcode.param_count() || code.len() < opcode.effect() {
+        return None;
+    }
+
+    let operands = (0..opcode.param_count())
+        .map(|n| {
+            let mode = match modes.get(n) {
+                Some(mode) => ParamMode::try_from(*mode).ok()?,
+                None => ParamMode::Positional,
+            };
+
+            if mode == ParamMode::Immediate && opcode.dest_param() == Some(n) {
+                return None;
+            }
+
+            Some(Operand {
+                mode,
+                value: code[n + 1],
+            })
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(Item::Instruction { opcode, operands })
+}
+
+/// Produces a listing of `code` by decoding it front to back, collecting any
+/// words that aren't instructions into `data` lines.
+pub fn disassemble(code: &[i64]) -> Vec<Line> {
+    let mut lines: Vec<Line> = Vec::new();
+    let mut address = 0;
+
+    while address < code.len() {
+        if let Some(item) = decode(&code[address..]) {
+            let len = item.len();
+            lines.push(Line { address, item });
+            address += len;
+            continue;
+        }
+
+        match lines.last_mut() {
+            Some(Line {
+                item: Item::Data(words),
+                ..
+            }) if words.len() < DATA_PER_LINE => words.push(code[address]),
+            _ => lines.push(Line {
+                address,
+                item: Item::Data(vec![code[address]]),
+            }),
+        }
+        address += 1;
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn listing(code: &[i64]) -> Vec<String> {
+        disassemble(code).iter().map(Line::to_string).collect()
+    }
+
+    #[test]
+    fn test_operands() {
+        assert_eq!(
+            listing(&[21101, 5, 100, 3, 99]),
+            vec!["0000: add #5, #100, [r+3]", "0004: hlt"]
+        );
+        assert_eq!(listing(&[204, -1]), vec!["0000: out [r-1]"]);
+        assert_eq!(listing(&[1006, 7, 0]), vec!["0000: jf [7], #0"]);
+    }
+
+    #[test]
+    fn test_quine() {
+        let code = [
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+
+        assert_eq!(
+            listing(&code),
+            vec![
+                "0000: arb #1",
+                "0002: out [r-1]",
+                "0004: add [100], #1, [100]",
+                "0008: eq [100], #16, [101]",
+                "0012: jf [101], #0",
+                "0015: hlt",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_data() {
+        assert_eq!(
+            listing(&[1105, 1, 7, 0, -5, 12345, 11101, 99]),
+            vec![
+                "0000: jt #1, #7",
+                "0003: data 0, -5, 12345, 11101",
+                "0007: hlt"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncated_instruction() {
+        assert_eq!(listing(&[99, 1, 0]), vec!["0000: hlt", "0001: data 1, 0"]);
+    }
+
+    #[test]
+    fn test_long_data() {
+        let lines = disassemble(&[0; 20]);
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[2].address, 16);
+    }
+}
@@ -0,0 +1,143 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::word::Word;
+
+/// The state of the VM at the instruction that caused an error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fault<W = i64> {
+    pub ip: usize,
+    pub instruction: W,
+    pub relative_base: W,
+}
+
+impl<W: Word> fmt::Display for Fault<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "at ip {} (instruction {}, relative base {})",
+            self.ip, self.instruction, self.relative_base
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IntcodeError<W = i64> {
+    /// The low two digits of the instruction are not a known opcode.
+    UnknownOpcode { fault: Fault<W>, opcode: i64 },
+    /// A parameter mode digit is not 0, 1 or 2.
+    InvalidParamMode {
+        fault: Fault<W>,
+        param: usize,
+        mode: i64,
+    },
+    /// An instruction tried to write to a parameter in immediate mode.
+    ImmediateWrite { fault: Fault<W>, param: usize },
+    /// A parameter resolved to an address below zero.
+    NegativeAddress {
+        fault: Fault<W>,
+        param: usize,
+        address: W,
+    },
+    /// A jump was taken to an address below zero.
+    NegativeJump { fault: Fault<W>, target: W },
+    /// A parameter resolved to an address past the memory limit, or one too
+    /// large to index memory with at all.
+    AddressOutOfRange {
+        fault: Fault<W>,
+        param: usize,
+        address: W,
+        limit: usize,
+    },
+    /// An addition or multiplication overflowed in `Arithmetic::Checked`
+    /// mode.
+    Overflow { fault: Fault<W> },
+    /// The instruction pointer moved past the end of the program without
+    /// encountering `Halt`.
+    RanOffEnd { fault: Fault<W> },
+    /// The program asked for more input than it was given.
+    InputExhausted { fault: Fault<W> },
+    /// `resume` was given input while it still held input from an earlier
+    /// call that stopped before reading it.
+    InputAlreadyHeld { fault: Fault<W> },
+    /// An instruction tried to write to a word of an instruction that had
+    /// already been executed, under `WxPolicy::Strict`.
+    CodeWrite { fault: Fault<W>, address: usize },
+    /// The program didn't finish within its instruction budget.
+    OutOfFuel { fault: Fault<W> },
+    /// The trace of an instruction couldn't be written.
+    TraceFailed { fault: Fault<W>, message: String },
+    /// Input couldn't be read or output couldn't be written.
+    IoFailed { fault: Fault<W>, message: String },
+}
+
+impl<W> IntcodeError<W> {
+    pub fn fault(&self) -> &Fault<W> {
+        match self {
+            IntcodeError::UnknownOpcode { fault, .. }
+            | IntcodeError::InvalidParamMode { fault, .. }
+            | IntcodeError::ImmediateWrite { fault, .. }
+            | IntcodeError::NegativeAddress { fault, .. }
+            | IntcodeError::NegativeJump { fault, .. }
+            | IntcodeError::AddressOutOfRange { fault, .. }
+            | IntcodeError::Overflow { fault }
+            | IntcodeError::RanOffEnd { fault }
+            | IntcodeError::InputExhausted { fault }
+            | IntcodeError::InputAlreadyHeld { fault }
+            | IntcodeError::CodeWrite { fault, .. }
+            | IntcodeError::OutOfFuel { fault }
+            | IntcodeError::TraceFailed { fault, .. }
+            | IntcodeError::IoFailed { fault, .. } => fault,
+        }
+    }
+}
+
+impl<W: Word> fmt::Display for IntcodeError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IntcodeError::UnknownOpcode { opcode, .. } => write!(f, "Unknown opcode {}", opcode)?,
+            IntcodeError::InvalidParamMode { param, mode, .. } => {
+                write!(f, "Invalid mode {} for parameter {}", mode, param)?
+            }
+            IntcodeError::ImmediateWrite { param, .. } => {
+                write!(f, "Write to parameter {} in immediate mode", param)?
+            }
+            IntcodeError::NegativeAddress { param, address, .. } => write!(
+                f,
+                "Parameter {} refers to negative address {}",
+                param, address
+            )?,
+            IntcodeError::NegativeJump { target, .. } => {
+                write!(f, "Jump to negative address {}", target)?
+            }
+            IntcodeError::AddressOutOfRange {
+                param,
+                address,
+                limit,
+                ..
+            } => write!(
+                f,
+                "Parameter {} refers to address {}, past the memory limit of {}",
+                param, address, limit
+            )?,
+            IntcodeError::Overflow { .. } => write!(f, "Arithmetic overflow")?,
+            IntcodeError::RanOffEnd { .. } => write!(f, "Didn't encounter Halt")?,
+            IntcodeError::InputExhausted { .. } => write!(f, "Unexpected end of input")?,
+            IntcodeError::InputAlreadyHeld { .. } => {
+                write!(f, "Input given while earlier input is still held")?
+            }
+            IntcodeError::CodeWrite { address, .. } => {
+                write!(f, "Write to executed instruction at address {}", address)?
+            }
+            IntcodeError::OutOfFuel { .. } => write!(f, "Ran out of fuel")?,
+            IntcodeError::TraceFailed { message, .. } => {
+                write!(f, "Failed to write trace: {}", message)?
+            }
+            IntcodeError::IoFailed { message, .. } => write!(f, "I/O failed: {}", message)?,
+        }
+
+        write!(f, " {}", self.fault())
+    }
+}
+
+impl<W: Word> Error for IntcodeError<W> {}
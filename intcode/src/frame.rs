@@ -0,0 +1,215 @@
+//! Reading outputs in fixed-size groups, for programs that describe each
+//! thing they print with several numbers, like a position and a colour.
+
+use std::convert::TryInto;
+use std::error::Error;
+use std::fmt;
+
+use crate::error::IntcodeError;
+use crate::vm::{ExecutionStatus, VM};
+use crate::word::Word;
+
+/// What the program did between frames.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame<const N: usize, W = i64> {
+    Output([W; N]),
+    AwaitingInput,
+    Complete,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameError<W = i64> {
+    Intcode(IntcodeError<W>),
+    /// The program stopped partway through a frame, ran out of fuel, or
+    /// stopped between frames when there was no way to carry on.
+    Incomplete {
+        expected: usize,
+        outputs: Vec<W>,
+        status: ExecutionStatus<W>,
+    },
+}
+
+impl<W: Word> fmt::Display for FrameError<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FrameError::Intcode(e) => write!(f, "{}", e),
+            FrameError::Incomplete {
+                expected,
+                outputs,
+                status,
+            } => write!(
+                f,
+                "Expected a frame of {} outputs, but the program stopped with {:?} after {:?}",
+                expected, status, outputs
+            ),
+        }
+    }
+}
+
+impl<W: Word> Error for FrameError<W> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FrameError::Intcode(e) => Some(e),
+            FrameError::Incomplete { .. } => None,
+        }
+    }
+}
+
+impl<W> From<IntcodeError<W>> for FrameError<W> {
+    fn from(e: IntcodeError<W>) -> Self {
+        FrameError::Intcode(e)
+    }
+}
+
+impl<W: Word> VM<W> {
+    /// Resumes with `input` and collects the next `N` outputs. If the program
+    /// needs input or halts before the first of them, that is returned
+    /// instead, but stopping after it, or running out of fuel at any point,
+    /// is an error.
+    pub fn read_outputs<const N: usize>(
+        &mut self,
+        input: Option<W>,
+    ) -> Result<Frame<N, W>, FrameError<W>> {
+        assert!(N > 0, "Frames must have at least one output");
+
+        let mut outputs = Vec::with_capacity(N);
+        let mut status = self.resume(input)?;
+
+        loop {
+            status = match status {
+                ExecutionStatus::Output(n) => {
+                    outputs.push(n);
+
+                    if outputs.len() == N {
+                        return Ok(Frame::Output(outputs.try_into().unwrap()));
+                    }
+                    self.resume(None)?
+                }
+                ExecutionStatus::Watchpoint(_) => self.resume(None)?,
+
+                ExecutionStatus::AwaitingInput if outputs.is_empty() => {
+                    return Ok(Frame::AwaitingInput)
+                }
+                ExecutionStatus::Complete if outputs.is_empty() => return Ok(Frame::Complete),
+                status => {
+                    return Err(FrameError::Incomplete {
+                        expected: N,
+                        outputs,
+                        status,
+                    })
+                }
+            };
+        }
+    }
+
+    /// Iterates over frames of `N` outputs until the program halts, for
+    /// programs that don't read input.
+    pub fn frames<const N: usize>(&mut self) -> Frames<'_, N, W> {
+        Frames {
+            vm: self,
+            done: false,
+        }
+    }
+}
+
+/// The frames of a program that doesn't read input, from `VM::frames`.
+#[derive(Debug)]
+pub struct Frames<'a, const N: usize, W = i64> {
+    vm: &'a mut VM<W>,
+    done: bool,
+}
+
+impl<const N: usize, W: Word> Iterator for Frames<'_, N, W> {
+    type Item = Result<[W; N], FrameError<W>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let status = match self.vm.read_outputs::<N>(None) {
+            Ok(Frame::Output(frame)) => return Some(Ok(frame)),
+            Ok(Frame::Complete) => {
+                self.done = true;
+                return None;
+            }
+            Ok(Frame::AwaitingInput) => ExecutionStatus::AwaitingInput,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(e));
+            }
+        };
+
+        self.done = true;
+        Some(Err(FrameError::Incomplete {
+            expected: N,
+            outputs: Vec::new(),
+            status,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_read_outputs() {
+        // Prints each input three times
+        let mut vm = VM::new(vec![3, 11, 4, 11, 4, 11, 4, 11, 1105, 1, 0, 0]);
+
+        assert_eq!(vm.read_outputs::<2>(None), Ok(Frame::AwaitingInput));
+        assert_eq!(vm.read_outputs::<2>(Some(5)), Ok(Frame::Output([5, 5])));
+        assert_eq!(
+            vm.read_outputs::<2>(None),
+            Err(FrameError::Incomplete {
+                expected: 2,
+                outputs: vec![5],
+                status: ExecutionStatus::AwaitingInput,
+            })
+        );
+
+        let mut vm = VM::new(vec![104, 1, 104, 2, 99]);
+        assert_eq!(vm.read_outputs::<2>(None), Ok(Frame::Output([1, 2])));
+        assert_eq!(vm.read_outputs::<2>(None), Ok(Frame::Complete));
+
+        let mut vm = VM::new(vec![104, 1, 104, 2, 99]);
+        vm.set_fuel(Some(1));
+        assert_eq!(
+            vm.read_outputs::<2>(None),
+            Err(FrameError::Incomplete {
+                expected: 2,
+                outputs: vec![1],
+                status: ExecutionStatus::OutOfFuel,
+            })
+        );
+    }
+
+    #[test]
+    fn test_frames() {
+        let mut vm = VM::new(vec![104, 1, 104, 2, 104, 3, 104, 4, 99]);
+        assert_eq!(
+            vm.frames::<2>().collect::<Vec<_>>(),
+            vec![Ok([1, 2]), Ok([3, 4])]
+        );
+
+        let mut vm = VM::new(vec![104, 1, 104, 2, 104, 3, 99]);
+        assert_eq!(
+            vm.frames::<2>().collect::<Vec<_>>(),
+            vec![
+                Ok([1, 2]),
+                Err(FrameError::Incomplete {
+                    expected: 2,
+                    outputs: vec![3],
+                    status: ExecutionStatus::Complete,
+                })
+            ]
+        );
+
+        let mut vm = VM::new(vec![3, 0, 99]);
+        assert!(matches!(
+            vm.frames::<2>().next(),
+            Some(Err(FrameError::Incomplete { .. }))
+        ));
+    }
+}
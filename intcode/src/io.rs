@@ -0,0 +1,223 @@
+//! Ways of connecting a program's input and output to the outside world, for
+//! use with `VM::run_with`.
+
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender};
+
+use crate::word::Word;
+
+pub trait IntcodeIo<W = i64> {
+    /// Produces the next value for the program to read, or `None` if there
+    /// won't be any more.
+    fn input(&mut self) -> io::Result<Option<W>>;
+
+    fn output(&mut self, value: W) -> io::Result<()>;
+}
+
+/// Reads input from a queue and collects output into a `Vec`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueueIo<W = i64> {
+    pub input: VecDeque<W>,
+    pub output: Vec<W>,
+}
+
+impl<W> QueueIo<W> {
+    pub fn new<T: Into<VecDeque<W>>>(input: T) -> Self {
+        Self {
+            input: input.into(),
+            output: Vec::new(),
+        }
+    }
+}
+
+impl<W: Word> IntcodeIo<W> for QueueIo<W> {
+    fn input(&mut self) -> io::Result<Option<W>> {
+        Ok(self.input.pop_front())
+    }
+
+    fn output(&mut self, value: W) -> io::Result<()> {
+        self.output.push(value);
+        Ok(())
+    }
+}
+
+/// Calls one closure for each input and another for each output.
+pub struct FnIo<I, O> {
+    input: I,
+    output: O,
+}
+
+impl<I, O> FnIo<I, O> {
+    pub fn new<W>(input: I, output: O) -> Self
+    where
+        I: FnMut() -> Option<W>,
+        O: FnMut(W),
+    {
+        Self { input, output }
+    }
+}
+
+impl<I, O, W> IntcodeIo<W> for FnIo<I, O>
+where
+    I: FnMut() -> Option<W>,
+    O: FnMut(W),
+{
+    fn input(&mut self) -> io::Result<Option<W>> {
+        Ok((self.input)())
+    }
+
+    fn output(&mut self, value: W) -> io::Result<()> {
+        (self.output)(value);
+        Ok(())
+    }
+}
+
+/// Prompts for each input on stdout and reads it as a line from stdin, and
+/// prints each output on a line of its own.
+#[derive(Debug, Clone)]
+pub struct StdIo {
+    prompt: String,
+}
+
+impl StdIo {
+    pub fn new() -> Self {
+        Self::with_prompt("> ")
+    }
+
+    pub fn with_prompt(prompt: &str) -> Self {
+        Self {
+            prompt: prompt.to_string(),
+        }
+    }
+}
+
+impl Default for StdIo {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<W: Word> IntcodeIo<W> for StdIo {
+    fn input(&mut self) -> io::Result<Option<W>> {
+        let mut stdout = io::stdout();
+        write!(stdout, "{}", self.prompt)?;
+        stdout.flush()?;
+
+        let mut line = String::new();
+        if io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        line.trim().parse().map(Some).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid input `{}`", line.trim()),
+            )
+        })
+    }
+
+    fn output(&mut self, value: W) -> io::Result<()> {
+        writeln!(io::stdout(), "{}", value)
+    }
+}
+
+/// Receives input from and sends output to other threads. Input runs out
+/// when every sender has hung up, and output fails once the receiver has.
+#[derive(Debug)]
+pub struct ChannelIo<W = i64> {
+    input: Receiver<W>,
+    output: Sender<W>,
+}
+
+impl<W> ChannelIo<W> {
+    pub fn new(input: Receiver<W>, output: Sender<W>) -> Self {
+        Self { input, output }
+    }
+}
+
+impl<W: Word> IntcodeIo<W> for ChannelIo<W> {
+    fn input(&mut self) -> io::Result<Option<W>> {
+        Ok(self.input.recv().ok())
+    }
+
+    fn output(&mut self, value: W) -> io::Result<()> {
+        self.output
+            .send(value)
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "Output channel closed"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::IntcodeError;
+    use crate::vm::VM;
+    use std::sync::mpsc;
+    use std::thread;
+
+    /// Adds up input until it reads a zero, printing the running total.
+    fn sum() -> VM {
+        VM::new(vec![
+            3, 15, // in [15]
+            1006, 15, 14, // jf [15], #14
+            1, 15, 16, 16, // add [15], [16], [16]
+            4, 16, // out [16]
+            1105, 1, 0,  // jt #1, #0
+            99, // hlt
+            0, 0,
+        ])
+    }
+
+    #[test]
+    fn test_queue() {
+        let mut io = QueueIo::new(vec![1, 2, 3, 0]);
+        sum().run_with(&mut io).unwrap();
+        assert_eq!(io.output, vec![1, 3, 6]);
+
+        let mut io = QueueIo::new(vec![1]);
+        assert!(matches!(
+            sum().run_with(&mut io),
+            Err(IntcodeError::InputExhausted { .. })
+        ));
+    }
+
+    #[test]
+    fn test_fn() {
+        let mut input = vec![0, 5, 4].into_iter().rev();
+        let mut output = Vec::new();
+        sum()
+            .run_with(&mut FnIo::new(|| input.next(), |n| output.push(n)))
+            .unwrap();
+
+        assert_eq!(output, vec![4, 9]);
+    }
+
+    #[test]
+    fn test_channel() {
+        let (to_vm, input) = mpsc::channel();
+        let (output, from_vm) = mpsc::channel();
+        let vm = thread::spawn(move || sum().run_with(&mut ChannelIo::new(input, output)));
+
+        to_vm.send(10).unwrap();
+        assert_eq!(from_vm.recv(), Ok(10));
+        to_vm.send(20).unwrap();
+        assert_eq!(from_vm.recv(), Ok(30));
+        drop(to_vm);
+
+        assert!(matches!(
+            vm.join().unwrap(),
+            Err(IntcodeError::InputExhausted { .. })
+        ));
+        assert!(from_vm.recv().is_err());
+
+        let (_to_vm, input) = mpsc::channel();
+        let (output, from_vm) = mpsc::channel();
+        drop(from_vm);
+        let mut vm = VM::new(vec![104, 1, 99]);
+        assert!(matches!(
+            vm.run_with(&mut ChannelIo::new(input, output)),
+            Err(IntcodeError::IoFailed { .. })
+        ));
+    }
+}
@@ -0,0 +1,44 @@
+mod arithmetic;
+mod ascii;
+pub mod asm;
+pub mod cfg;
+mod coverage;
+pub mod decompile;
+pub mod differential;
+pub mod disasm;
+mod error;
+mod frame;
+mod io;
+mod memory;
+mod opcode;
+mod profile;
+#[doc(hidden)]
+pub mod rng;
+mod snapshot;
+mod trace;
+mod vm;
+mod watch;
+mod word;
+mod wx;
+
+pub use arithmetic::Arithmetic;
+pub use ascii::{AsciiIo, AsciiOutput, AsciiVm};
+pub use coverage::Coverage;
+pub use error::{Fault, IntcodeError};
+pub use frame::{Frame, FrameError, Frames};
+pub use io::{ChannelIo, FnIo, IntcodeIo, QueueIo, StdIo};
+pub use memory::Memory;
+pub use opcode::{digits, DecodeError, Instruction, OpCode, ParamMode};
+pub use profile::{Profile, Span};
+pub use snapshot::{ParseSnapshotError, Snapshot};
+pub use vm::{Backend, ExecutionStatus, VM};
+pub use watch::{Access, WatchHit, Watchpoint};
+pub use word::{BigInt, ParseBigIntError, Word};
+pub use wx::{CodeWrite, WxPolicy};
+
+use std::num::ParseIntError;
+
+/// Parses a program in the comma-separated format of the puzzle inputs.
+pub fn parse_program(input: &str) -> Result<Vec<i64>, ParseIntError> {
+    input.trim().split(',').map(str::parse::<i64>).collect()
+}
@@ -0,0 +1,148 @@
+//! The VM's memory.
+//!
+//! Words from address 0 to a little past the end of the program are kept in a
+//! single vector, so the accesses most programs make cost a bounds check and
+//! an index. Anything further away is split into pages that are allocated the
+//! first time something nonzero is written to them, so a program that touches
+//! a few distant addresses only pays for a page each.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::word::Word;
+
+const PAGE_SIZE: usize = 1024;
+
+/// How much room there is past the end of the program before writes start
+/// going to pages.
+const DENSE_SLACK: usize = 4 * PAGE_SIZE;
+
+/// Always `PAGE_SIZE` words long.
+type Page<W> = Box<[W]>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Memory<W = i64> {
+    dense: Vec<W>,
+    pages: HashMap<usize, Page<W>>,
+    limit: Option<usize>,
+}
+
+impl<W: Word> Memory<W> {
+    /// Creates memory holding `code` from address 0, with every other word
+    /// zero.
+    pub fn new(mut code: Vec<W>) -> Self {
+        let dense_len = (code.len() + DENSE_SLACK).div_ceil(PAGE_SIZE) * PAGE_SIZE;
+        code.resize(dense_len, W::default());
+
+        Self {
+            dense: code,
+            pages: HashMap::new(),
+            limit: None,
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, idx: usize) -> W {
+        match self.dense.get(idx) {
+            Some(val) => val.clone(),
+            None => self.get_paged(idx),
+        }
+    }
+
+    #[inline]
+    pub fn set(&mut self, idx: usize, val: W) {
+        match self.dense.get_mut(idx) {
+            Some(word) => *word = val,
+            None => self.set_paged(idx, val),
+        }
+    }
+
+    // The paged halves are kept out of line so that the dense halves are
+    // small enough to inline into the VM.
+
+    #[cold]
+    fn get_paged(&self, idx: usize) -> W {
+        self.pages
+            .get(&(idx / PAGE_SIZE))
+            .map_or_else(W::default, |page| page[idx % PAGE_SIZE].clone())
+    }
+
+    #[cold]
+    fn set_paged(&mut self, idx: usize, val: W) {
+        if let Some(page) = self.pages.get_mut(&(idx / PAGE_SIZE)) {
+            page[idx % PAGE_SIZE] = val;
+        } else if !val.is_zero() {
+            let mut page = vec![W::default(); PAGE_SIZE].into_boxed_slice();
+            page[idx % PAGE_SIZE] = val;
+            self.pages.insert(idx / PAGE_SIZE, page);
+        }
+    }
+
+    /// The first `len` words, which must be within the area given to `new`.
+    pub(crate) fn prefix(&self, len: usize) -> &[W] {
+        &self.dense[..len]
+    }
+
+    /// Every nonzero word at `start` or above, by address.
+    pub fn nonzero_from(&self, start: usize) -> BTreeMap<usize, W> {
+        let dense = self.dense.iter().enumerate().skip(start);
+        let pages = self.pages.iter().flat_map(|(number, page)| {
+            page.iter()
+                .enumerate()
+                .map(move |(offset, val)| (number * PAGE_SIZE + offset, val))
+        });
+
+        dense
+            .chain(pages)
+            .filter(|(idx, val)| *idx >= start && !val.is_zero())
+            .map(|(idx, val)| (idx, val.clone()))
+            .collect()
+    }
+
+    /// The number of words programs may address, if limited.
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    pub fn set_limit(&mut self, limit: Option<usize>) {
+        self.limit = limit;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_get_set() {
+        let mut memory: Memory = Memory::new(vec![1, 2, 3]);
+
+        assert_eq!(memory.get(2), 3);
+        assert_eq!(memory.get(1_000_000_000_000), 0);
+
+        memory.set(1, 7);
+        memory.set(10, 8);
+        memory.set(1_000_000_000_000, 9);
+        memory.set(2_000_000_000_000, 0);
+
+        assert_eq!(memory.get(1), 7);
+        assert_eq!(memory.get(10), 8);
+        assert_eq!(memory.get(1_000_000_000_000), 9);
+        assert_eq!(memory.get(1_000_000_000_001), 0);
+        assert_eq!(memory.pages.len(), 1);
+        assert_eq!(memory.prefix(3), [1, 7, 3]);
+    }
+
+    #[test]
+    fn test_nonzero_from() {
+        let mut memory: Memory = Memory::new(vec![1, 0, 3]);
+        memory.set(20, 4);
+        memory.set(50_000, 5);
+        memory.set(50_001, 6);
+        memory.set(50_001, 0);
+
+        assert_eq!(
+            memory.nonzero_from(2).into_iter().collect::<Vec<_>>(),
+            vec![(2, 3), (20, 4), (50_000, 5)]
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use std::convert::TryFrom;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpCode {
+    Add = 1,
+    Mul = 2,
+    Input = 3,
+    Output = 4,
+    TJmp = 5,
+    FJmp = 6,
+    Lt = 7,
+    Eq = 8,
+    AdjustRelativeBase = 9,
+    Halt = 99,
+}
+
+impl OpCode {
+    pub const ALL: [OpCode; 10] = [
+        OpCode::Add,
+        OpCode::Mul,
+        OpCode::Input,
+        OpCode::Output,
+        OpCode::TJmp,
+        OpCode::FJmp,
+        OpCode::Lt,
+        OpCode::Eq,
+        OpCode::AdjustRelativeBase,
+        OpCode::Halt,
+    ];
+
+    /// The number of words occupied by the instruction, including the opcode
+    /// itself.
+    pub fn effect(&self) -> usize {
+        match self {
+            OpCode::Add | OpCode::Mul => 4,
+            OpCode::Input | OpCode::Output => 2,
+            OpCode::TJmp | OpCode::FJmp => 3,
+            OpCode::Lt | OpCode::Eq => 4,
+            OpCode::AdjustRelativeBase => 2,
+            OpCode::Halt => 1,
+        }
+    }
+
+    /// The number of parameters the instruction takes.
+    pub fn param_count(&self) -> usize {
+        self.effect() - 1
+    }
+
+    /// The parameter the instruction writes its result to, if any.
+    pub fn dest_param(&self) -> Option<usize> {
+        match self {
+            OpCode::Add | OpCode::Mul | OpCode::Lt | OpCode::Eq => Some(2),
+            OpCode::Input => Some(0),
+            _ => None,
+        }
+    }
+
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            OpCode::Add => "add",
+            OpCode::Mul => "mul",
+            OpCode::Input => "in",
+            OpCode::Output => "out",
+            OpCode::TJmp => "jt",
+            OpCode::FJmp => "jf",
+            OpCode::Lt => "lt",
+            OpCode::Eq => "eq",
+            OpCode::AdjustRelativeBase => "arb",
+            OpCode::Halt => "hlt",
+        }
+    }
+
+    pub fn from_mnemonic(mnemonic: &str) -> Option<OpCode> {
+        OpCode::ALL
+            .iter()
+            .copied()
+            .find(|opcode| opcode.mnemonic() == mnemonic)
+    }
+}
+
+impl TryFrom<i64> for OpCode {
+    type Error = i64;
+
+    fn try_from(n: i64) -> Result<OpCode, Self::Error> {
+        Ok(match n {
+            1 => OpCode::Add,
+            2 => OpCode::Mul,
+            3 => OpCode::Input,
+            4 => OpCode::Output,
+            5 => OpCode::TJmp,
+            6 => OpCode::FJmp,
+            7 => OpCode::Lt,
+            8 => OpCode::Eq,
+            9 => OpCode::AdjustRelativeBase,
+            99 => OpCode::Halt,
+
+            _ => return Err(n),
+        })
+    }
+}
+
+/// The decimal digits of `n`, least significant first.
+pub fn digits(n: i64) -> Vec<i64> {
+    let mut n = n;
+    let mut digits = Vec::new();
+
+    while n > 0 {
+        digits.push(n % 10);
+        n /= 10;
+    }
+
+    digits
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParamMode {
+    Positional = 0,
+    Immediate = 1,
+    Relative = 2,
+}
+
+impl TryFrom<i64> for ParamMode {
+    type Error = i64;
+
+    fn try_from(val: i64) -> Result<ParamMode, Self::Error> {
+        Ok(match val {
+            0 => ParamMode::Positional,
+            1 => ParamMode::Immediate,
+            2 => ParamMode::Relative,
+
+            _ => return Err(val),
+        })
+    }
+}
+
+/// An instruction word split into its opcode and the modes of its
+/// parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    pub opcode: OpCode,
+    /// Parameters the instruction word has no mode digit for are positional.
+    pub modes: [ParamMode; 3],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    UnknownOpcode(i64),
+    InvalidParamMode { param: usize, mode: i64 },
+}
+
+impl Instruction {
+    /// Decodes an instruction word. Mode digits past the third parameter have
+    /// to be valid even though no instruction has a use for them.
+    pub fn decode(raw_instruction: i64) -> Result<Instruction, DecodeError> {
+        let opcode = OpCode::try_from(raw_instruction % 100).map_err(DecodeError::UnknownOpcode)?;
+        let mut modes = [ParamMode::Positional; 3];
+        let mut rest = raw_instruction / 100;
+        let mut param = 0;
+
+        while rest > 0 {
+            let mode = ParamMode::try_from(rest % 10)
+                .map_err(|mode| DecodeError::InvalidParamMode { param, mode })?;
+            if let Some(slot) = modes.get_mut(param) {
+                *slot = mode;
+            }
+
+            rest /= 10;
+            param += 1;
+        }
+
+        Ok(Instruction { opcode, modes })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_opcode_round_trip() {
+        for n in -200..200 {
+            if let Ok(opcode) = OpCode::try_from(n) {
+                assert_eq!(opcode as i64, n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mnemonics() {
+        for opcode in &OpCode::ALL {
+            assert_eq!(OpCode::from_mnemonic(opcode.mnemonic()), Some(*opcode));
+        }
+        assert_eq!(OpCode::from_mnemonic("nop"), None);
+    }
+
+    #[test]
+    fn test_unknown_opcodes() {
+        for n in &[0, 10, 98, 100, -1, -99, i64::MIN, i64::MAX] {
+            assert_eq!(OpCode::try_from(*n), Err(*n));
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        use ParamMode::*;
+
+        assert_eq!(
+            Instruction::decode(21002),
+            Ok(Instruction {
+                opcode: OpCode::Mul,
+                modes: [Positional, Immediate, Relative],
+            })
+        );
+        assert_eq!(
+            Instruction::decode(99),
+            Ok(Instruction {
+                opcode: OpCode::Halt,
+                modes: [Positional; 3],
+            })
+        );
+        assert_eq!(
+            Instruction::decode(1111105).map(|i| i.modes),
+            Ok([Immediate; 3])
+        );
+        assert_eq!(
+            Instruction::decode(3101),
+            Err(DecodeError::InvalidParamMode { param: 1, mode: 3 })
+        );
+        assert_eq!(
+            Instruction::decode(5000101),
+            Err(DecodeError::InvalidParamMode { param: 4, mode: 5 })
+        );
+        assert_eq!(Instruction::decode(-1), Err(DecodeError::UnknownOpcode(-1)));
+    }
+}
@@ -0,0 +1,230 @@
+//! Counting where a program spends its time.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+use crate::disasm;
+use crate::opcode::OpCode;
+
+/// A stretch of execution ending with an input, output or `Halt`
+/// instruction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Span {
+    /// The instruction that ended the span.
+    pub end: OpCode,
+    /// The address of that instruction.
+    pub ip: usize,
+    /// The number of instructions executed, including the one that ended it.
+    pub instructions: u64,
+    /// The time since the previous span ended, including any time spent
+    /// outside the VM.
+    pub elapsed: Duration,
+}
+
+/// What a program did while being profiled.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Profile {
+    /// How many times the instruction at each address was executed.
+    pub by_address: BTreeMap<usize, u64>,
+    pub by_opcode: HashMap<OpCode, u64>,
+    pub spans: Vec<Span>,
+}
+
+fn percent(count: u64, total: u64) -> f64 {
+    100.0 * count as f64 / total.max(1) as f64
+}
+
+impl Profile {
+    pub fn instructions(&self) -> u64 {
+        self.by_address.values().sum()
+    }
+
+    /// The `top` most executed addresses, most executed first.
+    pub fn hot_spots(&self, top: usize) -> Vec<(usize, u64)> {
+        let mut hot_spots = self
+            .by_address
+            .iter()
+            .map(|(address, count)| (*address, *count))
+            .collect::<Vec<_>>();
+        hot_spots.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hot_spots.truncate(top);
+        hot_spots
+    }
+
+    /// Describes the `top` hot spots, every opcode and the I/O spans. If
+    /// `code` is given, each hot spot is shown with its instruction, so it
+    /// should be the program as it was at the end of the run.
+    pub fn report(&self, code: Option<&[i64]>, top: usize) -> String {
+        let total = self.instructions();
+        let mut report = String::new();
+
+        writeln!(report, "{} instructions executed", total).unwrap();
+
+        writeln!(report, "\nHot spots:").unwrap();
+        for (address, count) in self.hot_spots(top) {
+            write!(
+                report,
+                "{:04} {:>12} {:>6.2}%",
+                address,
+                count,
+                percent(count, total)
+            )
+            .unwrap();
+            if let Some(code) = code {
+                let instruction = code
+                    .get(address..)
+                    .and_then(disasm::decode)
+                    .map_or_else(|| "?".to_string(), |item| item.to_string());
+                write!(report, "  {}", instruction).unwrap();
+            }
+            writeln!(report).unwrap();
+        }
+
+        let mut by_opcode = self.by_opcode.iter().collect::<Vec<_>>();
+        by_opcode.sort_by(|a, b| b.1.cmp(a.1).then(a.0.mnemonic().cmp(b.0.mnemonic())));
+
+        writeln!(report, "\nOpcodes:").unwrap();
+        for (opcode, count) in by_opcode {
+            writeln!(
+                report,
+                "{:<4} {:>12} {:>6.2}%",
+                opcode.mnemonic(),
+                count,
+                percent(*count, total)
+            )
+            .unwrap();
+        }
+
+        writeln!(report, "\nI/O:").unwrap();
+        if self.spans.is_empty() {
+            writeln!(report, "none").unwrap();
+            return report;
+        }
+
+        let elapsed = self.spans.iter().map(|span| span.elapsed).sum::<Duration>();
+        let count = self.spans.len() as u32;
+        writeln!(
+            report,
+            "{} spans between I/O, {:?} in all, {:?} and {} instructions on average",
+            count,
+            elapsed,
+            elapsed / count,
+            total / u64::from(count)
+        )
+        .unwrap();
+
+        let mut slowest = self.spans.iter().collect::<Vec<_>>();
+        slowest.sort_by_key(|span| Reverse(span.elapsed));
+        for span in slowest.iter().take(top) {
+            writeln!(
+                report,
+                "{:>12?} {:>12} instructions up to {} at {:04}",
+                span.elapsed,
+                span.instructions,
+                span.end.mnemonic(),
+                span.ip
+            )
+            .unwrap();
+        }
+
+        report
+    }
+}
+
+/// Builds up a `Profile` as instructions are executed.
+#[derive(Debug)]
+pub(crate) struct Profiler {
+    pub profile: Profile,
+    /// The instruction being executed, counted once it completes.
+    pub pending: Option<(usize, OpCode)>,
+    span_start: Instant,
+    span_instructions: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            profile: Profile::default(),
+            pending: None,
+            span_start: Instant::now(),
+            span_instructions: 0,
+        }
+    }
+
+    /// Counts the pending instruction, if there is one.
+    pub fn finish(&mut self) {
+        let (ip, opcode) = match self.pending.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+
+        *self.profile.by_address.entry(ip).or_insert(0) += 1;
+        *self.profile.by_opcode.entry(opcode).or_insert(0) += 1;
+        self.span_instructions += 1;
+
+        if let OpCode::Input | OpCode::Output | OpCode::Halt = opcode {
+            let now = Instant::now();
+            self.profile.spans.push(Span {
+                end: opcode,
+                ip,
+                instructions: self.span_instructions,
+                elapsed: now - self.span_start,
+            });
+            self.span_start = now;
+            self.span_instructions = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_report() {
+        let profile = Profile {
+            by_address: vec![(0, 1), (2, 3), (7, 1), (8, 3)].into_iter().collect(),
+            by_opcode: vec![(OpCode::Output, 1), (OpCode::Add, 6), (OpCode::Halt, 1)]
+                .into_iter()
+                .collect(),
+            spans: vec![
+                Span {
+                    end: OpCode::Output,
+                    ip: 0,
+                    instructions: 1,
+                    elapsed: Duration::from_millis(1),
+                },
+                Span {
+                    end: OpCode::Halt,
+                    ip: 7,
+                    instructions: 7,
+                    elapsed: Duration::from_millis(3),
+                },
+            ],
+        };
+
+        assert_eq!(profile.hot_spots(2), vec![(2, 3), (8, 3)]);
+        assert_eq!(
+            profile.report(Some(&[104, 1, 1001, 9, 1, 9, 0, 99]), 2),
+            concat!(
+                "8 instructions executed\n",
+                "\n",
+                "Hot spots:\n",
+                "0002            3  37.50%  add [9], #1, [9]\n",
+                "0008            3  37.50%  ?\n",
+                "\n",
+                "Opcodes:\n",
+                "add             6  75.00%\n",
+                "hlt             1  12.50%\n",
+                "out             1  12.50%\n",
+                "\n",
+                "I/O:\n",
+                "2 spans between I/O, 4ms in all, 2ms and 4 instructions on average\n",
+                "         3ms            7 instructions up to hlt at 0007\n",
+                "         1ms            1 instructions up to out at 0000\n",
+            )
+        );
+    }
+}
@@ -0,0 +1,225 @@
+//! The saved state of a VM.
+//!
+//! Snapshots are written as text, one field per line:
+//!
+//! ```text
+//! intcode-snapshot 2
+//! ip 12
+//! relative_base 100
+//! code 1,2,3
+//! extra 1000:5,1001:7
+//! input 4
+//! ```
+//!
+//! `input` is left empty unless the VM was holding input it hadn't read yet.
+//!
+//! The number on the first line is the version of the format, and is to be
+//! bumped whenever the layout changes so old snapshots are rejected rather than
+//! misread.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::word::Word;
+
+const HEADER: &str = "intcode-snapshot";
+const VERSION: u32 = 2;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot<W = i64> {
+    pub ip: usize,
+    pub relative_base: W,
+    pub code: Vec<W>,
+    /// Memory past the end of the program that has been written to.
+    pub extra_memory: BTreeMap<usize, W>,
+    /// Input given to `resume` that the program hadn't read when it stopped.
+    pub held_input: Option<W>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseSnapshotError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseSnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ParseSnapshotError {}
+
+fn join<T: ToString>(items: impl Iterator<Item = T>) -> String {
+    items
+        .map(|item| item.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl<W: Word> fmt::Display for Snapshot<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", HEADER, VERSION)?;
+        writeln!(f, "ip {}", self.ip)?;
+        writeln!(f, "relative_base {}", self.relative_base)?;
+        writeln!(f, "code {}", join(self.code.iter()))?;
+        writeln!(
+            f,
+            "extra {}",
+            join(
+                self.extra_memory
+                    .iter()
+                    .map(|(address, value)| format!("{}:{}", address, value))
+            )
+        )?;
+        writeln!(f, "input {}", join(self.held_input.iter()))
+    }
+}
+
+impl<W: Word> FromStr for Snapshot<W> {
+    type Err = ParseSnapshotError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().enumerate();
+
+        let mut field = |name: &str| {
+            let (i, line) = lines.next().ok_or_else(|| ParseSnapshotError {
+                line: s.lines().count() + 1,
+                message: format!("Missing `{}`", name),
+            })?;
+            let error = move |message: String| ParseSnapshotError {
+                line: i + 1,
+                message,
+            };
+
+            match line.split_once(' ').unwrap_or((line, "")) {
+                (key, value) if key == name => Ok((value.trim().to_string(), error)),
+                _ => Err(error(format!("Expected `{}`", name))),
+            }
+        };
+
+        macro_rules! parse {
+            ($value:expr, $error:expr) => {
+                $value
+                    .parse()
+                    .map_err(|_| $error(format!("Invalid number `{}`", $value)))?
+            };
+        }
+
+        let (version, error) = field(HEADER)?;
+        let version_number: u32 = parse!(version, error);
+        if version_number != VERSION {
+            return Err(error(format!("Unsupported version {}", version)));
+        }
+
+        let (ip, error) = field("ip")?;
+        let ip = parse!(ip, error);
+
+        let (relative_base, error) = field("relative_base")?;
+        let relative_base = parse!(relative_base, error);
+
+        let (code, error) = field("code")?;
+        let code = code
+            .split(',')
+            .filter(|word| !word.is_empty())
+            .map(|word| Ok(parse!(word, error)))
+            .collect::<Result<_, _>>()?;
+
+        let (extra, error) = field("extra")?;
+        let extra_memory = extra
+            .split(',')
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (address, value) = entry
+                    .split_once(':')
+                    .ok_or_else(|| error(format!("Invalid entry `{}`", entry)))?;
+                Ok((parse!(address, error), parse!(value, error)))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let (input, error) = field("input")?;
+        let held_input = match input.as_str() {
+            "" => None,
+            input => Some(parse!(input, error)),
+        };
+
+        Ok(Snapshot {
+            ip,
+            relative_base,
+            code,
+            extra_memory,
+            held_input,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn snapshot() -> Snapshot {
+        Snapshot {
+            ip: 4,
+            relative_base: -3,
+            code: vec![1, 0, 0, 0, 99],
+            extra_memory: vec![(1001, 7), (1000, -5)].into_iter().collect(),
+            held_input: Some(12),
+        }
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(
+            snapshot().to_string(),
+            "intcode-snapshot 2\nip 4\nrelative_base -3\ncode 1,0,0,0,99\nextra 1000:-5,1001:7\ninput 12\n"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        assert_eq!(snapshot().to_string().parse(), Ok(snapshot()));
+
+        let empty: Snapshot = Snapshot {
+            ip: 0,
+            relative_base: 0,
+            code: Vec::new(),
+            extra_memory: BTreeMap::new(),
+            held_input: None,
+        };
+        assert_eq!(empty.to_string().parse(), Ok(empty));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |s: &str, line, message: &str| {
+            assert_eq!(
+                s.parse::<Snapshot>(),
+                Err(ParseSnapshotError {
+                    line,
+                    message: message.to_string(),
+                })
+            );
+        };
+
+        error("intcode-snapshot 1\n", 1, "Unsupported version 1");
+        error("intcode-snapshot 2\nip 4\n", 3, "Missing `relative_base`");
+        error("intcode-snapshot 2\nrelative_base 4\n", 2, "Expected `ip`");
+        error(
+            "intcode-snapshot 2\nip 0\nrelative_base 0\ncode 1,x\nextra\n",
+            4,
+            "Invalid number `x`",
+        );
+        error(
+            "intcode-snapshot 2\nip 0\nrelative_base 0\ncode 99\nextra 5\n",
+            5,
+            "Invalid entry `5`",
+        );
+        error(
+            "intcode-snapshot 2\nip 0\nrelative_base 0\ncode 99\nextra\ninput 1,2\n",
+            6,
+            "Invalid number `1,2`",
+        );
+    }
+}
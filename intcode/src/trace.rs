@@ -0,0 +1,149 @@
+use std::fmt;
+use std::io::Write;
+
+use crate::error::{Fault, IntcodeError};
+use crate::opcode::{OpCode, ParamMode};
+use crate::word::Word;
+
+/// What a single instruction did, as written to a trace.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Record<W> {
+    pub ip: usize,
+    pub instruction: W,
+    pub opcode: OpCode,
+    pub relative_base: W,
+    pub operands: Vec<(ParamMode, W)>,
+    pub reads: Vec<(usize, W)>,
+    pub writes: Vec<(usize, W)>,
+}
+
+fn mode_name(mode: ParamMode) -> &'static str {
+    match mode {
+        ParamMode::Positional => "positional",
+        ParamMode::Immediate => "immediate",
+        ParamMode::Relative => "relative",
+    }
+}
+
+fn accesses<W: Word>(accesses: &[(usize, W)]) -> String {
+    accesses
+        .iter()
+        .map(|(address, value)| format!(r#"{{"address":{},"value":{}}}"#, address, value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl<W: Word> Record<W> {
+    pub fn fault(&self) -> Fault<W> {
+        Fault {
+            ip: self.ip,
+            instruction: self.instruction.clone(),
+            relative_base: self.relative_base.clone(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let operands = self
+            .operands
+            .iter()
+            .map(|(mode, value)| format!(r#"{{"mode":"{}","value":{}}}"#, mode_name(*mode), value))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            r#"{{"ip":{},"instruction":{},"opcode":"{}","relative_base":{},"operands":[{}],"reads":[{}],"writes":[{}]}}"#,
+            self.ip,
+            self.instruction,
+            self.opcode.mnemonic(),
+            self.relative_base,
+            operands,
+            accesses(&self.reads),
+            accesses(&self.writes),
+        )
+    }
+}
+
+/// Writes a JSON object per executed instruction to some output.
+pub(crate) struct Tracer<W> {
+    out: Box<dyn Write + Send>,
+    pub record: Option<Record<W>>,
+}
+
+impl<W: Word> Tracer<W> {
+    pub fn new<O: Write + Send + 'static>(out: O) -> Self {
+        Self {
+            out: Box::new(out),
+            record: None,
+        }
+    }
+
+    pub fn read(&mut self, address: usize, value: W) {
+        if let Some(record) = &mut self.record {
+            record.reads.push((address, value));
+        }
+    }
+
+    pub fn write(&mut self, address: usize, value: W) {
+        if let Some(record) = &mut self.record {
+            record.writes.push((address, value));
+        }
+    }
+
+    /// Writes out the record of the current instruction, if there is one.
+    pub fn finish(&mut self) -> Result<(), IntcodeError<W>> {
+        if let Some(record) = self.record.take() {
+            writeln!(self.out, "{}", record.to_json()).map_err(|e| IntcodeError::TraceFailed {
+                fault: record.fault(),
+                message: e.to_string(),
+            })?;
+        }
+
+        Ok(())
+    }
+
+    pub fn into_inner(mut self) -> Box<dyn Write + Send> {
+        let _ = self.out.flush();
+        self.out
+    }
+}
+
+impl<W: fmt::Debug> fmt::Debug for Tracer<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Tracer")
+            .field("record", &self.record)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let record: Record<i64> = Record {
+            ip: 4,
+            instruction: 1201,
+            opcode: OpCode::Add,
+            relative_base: 7,
+            operands: vec![
+                (ParamMode::Positional, 9),
+                (ParamMode::Relative, -2),
+                (ParamMode::Positional, 9),
+            ],
+            reads: vec![(9, 1), (5, 2)],
+            writes: vec![(9, 3)],
+        };
+
+        assert_eq!(
+            record.to_json(),
+            concat!(
+                r#"{"ip":4,"instruction":1201,"opcode":"add","relative_base":7,"#,
+                r#""operands":[{"mode":"positional","value":9},{"mode":"relative","value":-2},"#,
+                r#"{"mode":"positional","value":9}],"#,
+                r#""reads":[{"address":9,"value":1},{"address":5,"value":2}],"#,
+                r#""writes":[{"address":9,"value":3}]}"#,
+            )
+        );
+    }
+}
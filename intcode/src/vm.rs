@@ -0,0 +1,1481 @@
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::io::Write;
+
+use crate::arithmetic::Arithmetic;
+use crate::coverage::Coverage;
+use crate::error::{Fault, IntcodeError};
+use crate::io::{IntcodeIo, QueueIo};
+use crate::memory::Memory;
+use crate::opcode::{DecodeError, Instruction, OpCode, ParamMode};
+use crate::profile::{Profile, Profiler};
+use crate::snapshot::Snapshot;
+use crate::trace::{Record, Tracer};
+use crate::watch::{Access, WatchHit, Watchpoint};
+use crate::word::Word;
+use crate::wx::{CodeWrite, WxGuard, WxPolicy};
+
+mod compiled;
+
+pub use compiled::Backend;
+use compiled::Compiled;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExecutionStatus<W = i64> {
+    Output(W),
+    AwaitingInput,
+    Complete,
+    /// The instruction budget set with `VM::set_fuel` has been used up.
+    /// Execution carries on from the same place once more is given.
+    OutOfFuel,
+    /// Instructions accessed watched memory, as described by the hits.
+    Watchpoint(Vec<WatchHit<W>>),
+}
+
+/// An Intcode machine whose memory holds words of type `W`.
+#[derive(Debug)]
+pub struct VM<W = i64> {
+    memory: Memory<W>,
+    ip: usize,
+    len: usize,
+    /// The instructions decoded so far, by address within the program.
+    decoded: Vec<Option<Instruction>>,
+    backend: Backend,
+    compiled: Compiled<W>,
+    relative_base: W,
+    arithmetic: Arithmetic,
+    tracer: Option<Tracer<W>>,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+    wx: Option<WxGuard<W>>,
+    fuel: Option<u64>,
+    /// Input passed to `resume` that was not read before the fuel ran out.
+    held_input: Option<W>,
+    watchpoints: HashSet<Watchpoint>,
+    /// Watchpoints hit by the current instruction, or by an output
+    /// instruction that has yet to be reported.
+    watch_hits: Vec<WatchHit<W>>,
+}
+
+impl VM {
+    /// A machine with the usual 64-bit words. Use `VM::from_words` for any
+    /// other kind.
+    pub fn new(code: Vec<i64>) -> Self {
+        Self::from_words(code)
+    }
+}
+
+/// The low two decimal digits of a word too large to decode, which is what
+/// `UnknownOpcode` reports.
+fn low_digits<W: Word>(word: &W) -> i64 {
+    let digits = word.to_string();
+    let low: i64 = digits[digits.len() - 2..].parse().unwrap();
+
+    if word.is_negative() {
+        -low
+    } else {
+        low
+    }
+}
+
+impl<W: Word> VM<W> {
+    pub fn from_words(code: Vec<W>) -> Self {
+        Self {
+            len: code.len(),
+            decoded: vec![None; code.len()],
+            backend: Backend::default(),
+            compiled: Compiled::new(code.len()),
+            memory: Memory::new(code),
+            ip: 0,
+            relative_base: W::default(),
+            arithmetic: Arithmetic::default(),
+            tracer: None,
+            profiler: None,
+            coverage: None,
+            wx: None,
+            fuel: None,
+            held_input: None,
+            watchpoints: HashSet::new(),
+            watch_hits: Vec::new(),
+        }
+    }
+
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    pub fn relative_base(&self) -> &W {
+        &self.relative_base
+    }
+
+    /// The memory holding the program, as modified by the program so far.
+    pub fn code(&self) -> &[W] {
+        self.memory.prefix(self.len)
+    }
+
+    /// The nonzero words past the end of the program.
+    pub fn extra_memory(&self) -> BTreeMap<usize, W> {
+        self.memory.nonzero_from(self.len)
+    }
+
+    pub fn memory(&self) -> &Memory<W> {
+        &self.memory
+    }
+
+    /// Limits programs to addresses below `limit`, past which they fail with
+    /// `AddressOutOfRange`. `None` removes the limit.
+    pub fn set_memory_limit(&mut self, limit: Option<usize>) {
+        self.memory.set_limit(limit);
+    }
+
+    pub fn get(&self, idx: usize) -> W {
+        self.memory.get(idx)
+    }
+
+    pub fn set(&mut self, idx: usize, val: W) {
+        if let Some(decoded) = self.decoded.get_mut(idx) {
+            *decoded = None;
+            self.compiled.write(idx);
+        }
+        self.memory.set(idx, val);
+    }
+
+    /// Captures the state of the machine, so it can be restored later.
+    pub fn snapshot(&self) -> Snapshot<W> {
+        Snapshot {
+            ip: self.ip,
+            relative_base: self.relative_base.clone(),
+            code: self.code().to_vec(),
+            extra_memory: self.extra_memory(),
+            held_input: self.held_input.clone(),
+        }
+    }
+
+    /// Puts the machine back in the state captured by `snapshot`. Tracing,
+    /// profiling, coverage, code write detection, watchpoints, the backend
+    /// and the memory limit are left as they are.
+    pub fn restore(&mut self, snapshot: &Snapshot<W>) {
+        let limit = self.memory.limit();
+
+        self.len = snapshot.code.len();
+        self.decoded = vec![None; self.len];
+        self.compiled = Compiled::new(self.len);
+        self.memory = Memory::new(snapshot.code.clone());
+        self.memory.set_limit(limit);
+        for (idx, val) in &snapshot.extra_memory {
+            self.memory.set(*idx, val.clone());
+        }
+        self.ip = snapshot.ip;
+        self.relative_base = snapshot.relative_base.clone();
+        self.held_input = snapshot.held_input.clone();
+        self.watch_hits.clear();
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    /// Switches the way instructions are executed from now on. The program
+    /// carries on from the same place either way.
+    pub fn set_backend(&mut self, backend: Backend) {
+        self.backend = backend;
+    }
+
+    pub fn arithmetic(&self) -> Arithmetic {
+        self.arithmetic
+    }
+
+    /// Chooses what happens when arithmetic overflows.
+    pub fn set_arithmetic(&mut self, arithmetic: Arithmetic) {
+        self.arithmetic = arithmetic;
+    }
+
+    /// Limits the machine to executing `fuel` more instructions, after which
+    /// `resume` returns `OutOfFuel`. `None` removes the limit.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.fuel = fuel;
+    }
+
+    /// The number of instructions left to execute, if limited.
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Writes a line of JSON to `out` for every instruction executed from now
+    /// on, describing its operands and the memory it read and wrote.
+    pub fn trace_to<O: Write + Send + 'static>(&mut self, out: O) {
+        self.tracer = Some(Tracer::new(out));
+    }
+
+    /// Stops tracing, returning the output the trace was written to.
+    pub fn stop_tracing(&mut self) -> Option<Box<dyn Write + Send>> {
+        self.tracer.take().map(Tracer::into_inner)
+    }
+
+    /// Counts the instructions executed from now on by address and opcode,
+    /// and times the stretches between input and output. Starting again
+    /// throws away the profile so far.
+    pub fn start_profiling(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    /// The profile so far, if profiling.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profiler.as_ref().map(|profiler| &profiler.profile)
+    }
+
+    pub fn stop_profiling(&mut self) -> Option<Profile> {
+        self.profiler.take().map(|profiler| profiler.profile)
+    }
+
+    /// Records the addresses executed, read and written from now on.
+    /// Starting again throws away what has been recorded so far.
+    pub fn start_coverage(&mut self) {
+        self.coverage = Some(Coverage::default());
+    }
+
+    /// The coverage so far, if recording it.
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub fn stop_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    /// Starts watching for the program writing to words of instructions it
+    /// has already executed, either opcodes or parameters. Writes made with
+    /// `set` aren't counted.
+    pub fn start_code_write_detection(&mut self, policy: WxPolicy) {
+        self.wx = Some(WxGuard::new(policy));
+    }
+
+    /// The writes to executed instructions so far, if watching for them.
+    pub fn code_writes(&self) -> Option<&[CodeWrite<W>]> {
+        self.wx.as_ref().map(|wx| &wx.writes[..])
+    }
+
+    pub fn stop_code_write_detection(&mut self) -> Option<Vec<CodeWrite<W>>> {
+        self.wx.take().map(|wx| wx.writes)
+    }
+
+    /// Pauses execution whenever `address` is accessed in the given way,
+    /// whether it is inside the program or past its end.
+    pub fn watch(&mut self, address: usize, access: Access) {
+        self.watchpoints.insert(Watchpoint { address, access });
+    }
+
+    /// Removes a watchpoint, returning whether it was set.
+    pub fn unwatch(&mut self, address: usize, access: Access) -> bool {
+        self.watchpoints.remove(&Watchpoint { address, access })
+    }
+
+    pub fn watchpoints(&self) -> &HashSet<Watchpoint> {
+        &self.watchpoints
+    }
+
+    fn watched(&mut self, address: usize, access: Access, old: W, new: W) {
+        if self.watchpoints.contains(&Watchpoint { address, access }) {
+            self.watch_hits.push(WatchHit {
+                ip: self.ip,
+                address,
+                access,
+                old,
+                new,
+            });
+        }
+    }
+
+    fn read(&mut self, idx: usize) -> W {
+        let val = self.get(idx);
+        if let Some(tracer) = &mut self.tracer {
+            tracer.read(idx, val.clone());
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.read.insert(idx);
+        }
+        if !self.watchpoints.is_empty() {
+            self.watched(idx, Access::Read, val.clone(), val.clone());
+        }
+        val
+    }
+
+    fn write(&mut self, idx: usize, val: W) -> Result<(), IntcodeError<W>> {
+        if let Some(wx) = &self.wx {
+            if wx.executed(idx) {
+                if wx.policy == WxPolicy::Strict {
+                    return Err(IntcodeError::CodeWrite {
+                        fault: self.fault(),
+                        address: idx,
+                    });
+                }
+
+                let write = CodeWrite {
+                    ip: self.ip,
+                    address: idx,
+                    old: self.get(idx),
+                    new: val.clone(),
+                };
+                if let Some(wx) = &mut self.wx {
+                    wx.writes.push(write);
+                }
+            }
+        }
+        if let Some(tracer) = &mut self.tracer {
+            tracer.write(idx, val.clone());
+        }
+        if let Some(coverage) = &mut self.coverage {
+            coverage.written.insert(idx);
+        }
+        if !self.watchpoints.is_empty() {
+            let old = self.get(idx);
+            self.watched(idx, Access::Write, old, val.clone());
+        }
+        self.set(idx, val);
+        Ok(())
+    }
+
+    /// The state of the machine at the current instruction, for error
+    /// reporting.
+    pub(crate) fn fault(&self) -> Fault<W> {
+        Fault {
+            ip: self.ip,
+            instruction: self.get(self.ip),
+            relative_base: self.relative_base.clone(),
+        }
+    }
+
+    /// Turns the result of an arithmetic operation into an error if it
+    /// overflowed.
+    fn arith(&self, result: Option<W>) -> Result<W, IntcodeError<W>> {
+        result.ok_or_else(|| IntcodeError::Overflow {
+            fault: self.fault(),
+        })
+    }
+
+    /// The address `offset` refers to in relative mode.
+    fn relative(&self, offset: &W) -> Result<W, IntcodeError<W>> {
+        self.arith(offset.add(&self.relative_base, self.arithmetic))
+    }
+
+    fn address(&self, param: usize, address: W) -> Result<usize, IntcodeError<W>> {
+        if address.is_negative() {
+            return Err(IntcodeError::NegativeAddress {
+                fault: self.fault(),
+                param,
+                address,
+            });
+        }
+
+        let limit = self.memory.limit();
+        match address.to_i64() {
+            Some(idx) if limit.is_none_or(|limit| (idx as usize) < limit) => Ok(idx as usize),
+            _ => Err(IntcodeError::AddressOutOfRange {
+                fault: self.fault(),
+                param,
+                address,
+                limit: limit.unwrap_or(usize::MAX),
+            }),
+        }
+    }
+
+    /// The address a jump to `target` goes to. Targets too large to be an
+    /// address end up past the end of the program.
+    fn jump_target(&self, target: W) -> Result<usize, IntcodeError<W>> {
+        if target.is_negative() {
+            return Err(IntcodeError::NegativeJump {
+                fault: self.fault(),
+                target,
+            });
+        }
+
+        Ok(target.to_i64().map_or(usize::MAX, |target| target as usize))
+    }
+
+    /// Executes the instruction at `ip`, taking a value from `input` if it
+    /// reads one.
+    ///
+    /// Returns the status `resume` would stop with after this instruction, or
+    /// `None` if execution can carry on. Watchpoints hit by an instruction
+    /// that also produces output are reported by the following call, before
+    /// anything else is executed.
+    pub fn step(
+        &mut self,
+        input: &mut Option<W>,
+    ) -> Result<Option<ExecutionStatus<W>>, IntcodeError<W>> {
+        if !self.watch_hits.is_empty() {
+            let hits = std::mem::take(&mut self.watch_hits);
+            return Ok(Some(ExecutionStatus::Watchpoint(hits)));
+        }
+
+        if self.fuel == Some(0) {
+            return Ok(Some(ExecutionStatus::OutOfFuel));
+        }
+
+        let ip = self.ip;
+        let result = self.execute(input);
+
+        if let (Some(fuel), Ok(status)) = (&mut self.fuel, &result) {
+            if *status != Some(ExecutionStatus::AwaitingInput) {
+                *fuel -= 1;
+            }
+        }
+
+        if let (Some(coverage), Ok(status)) = (&mut self.coverage, &result) {
+            if *status != Some(ExecutionStatus::AwaitingInput) {
+                coverage.executed.insert(ip);
+            }
+        }
+
+        if let Some(profiler) = &mut self.profiler {
+            match result {
+                Ok(Some(ExecutionStatus::AwaitingInput)) | Err(_) => profiler.pending = None,
+                Ok(_) => profiler.finish(),
+            }
+        }
+
+        if let Some(tracer) = &mut self.tracer {
+            match result {
+                // Nothing happened, the instruction will be traced when it
+                // gets its input
+                Ok(Some(ExecutionStatus::AwaitingInput)) | Err(_) => tracer.record = None,
+                Ok(_) => tracer.finish()?,
+            }
+        }
+
+        match result {
+            Ok(None) if !self.watch_hits.is_empty() => {
+                let hits = std::mem::take(&mut self.watch_hits);
+                Ok(Some(ExecutionStatus::Watchpoint(hits)))
+            }
+            Err(e) => {
+                self.watch_hits.clear();
+                Err(e)
+            }
+            _ => result,
+        }
+    }
+
+    /// Decodes the instruction at `ip`, which must be inside the program,
+    /// reusing the result of last time unless the word has since changed.
+    fn decode(&mut self) -> Result<Instruction, IntcodeError<W>> {
+        if let Some(instruction) = self.decoded[self.ip] {
+            return Ok(instruction);
+        }
+
+        let word = self.get(self.ip);
+        let decoded = match word.to_i64() {
+            Some(word) => Instruction::decode(word),
+            None => Err(DecodeError::UnknownOpcode(low_digits(&word))),
+        };
+        let instruction = decoded.map_err(|e| match e {
+            DecodeError::UnknownOpcode(opcode) => IntcodeError::UnknownOpcode {
+                fault: self.fault(),
+                opcode,
+            },
+            DecodeError::InvalidParamMode { param, mode } => IntcodeError::InvalidParamMode {
+                fault: self.fault(),
+                param,
+                mode,
+            },
+        })?;
+        self.decoded[self.ip] = Some(instruction);
+
+        Ok(instruction)
+    }
+
+    #[allow(clippy::cognitive_complexity)]
+    fn execute(
+        &mut self,
+        input: &mut Option<W>,
+    ) -> Result<Option<ExecutionStatus<W>>, IntcodeError<W>> {
+        if self.ip >= self.len {
+            return Err(IntcodeError::RanOffEnd {
+                fault: self.fault(),
+            });
+        }
+
+        let Instruction {
+            opcode: instruction,
+            modes,
+        } = self.decode()?;
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.pending = Some((self.ip, instruction));
+        }
+
+        if let Some(wx) = &mut self.wx {
+            wx.execute(self.ip, instruction.effect());
+        }
+
+        if self.tracer.is_some() {
+            let record = Record {
+                ip: self.ip,
+                instruction: self.get(self.ip),
+                opcode: instruction,
+                relative_base: self.relative_base.clone(),
+                operands: (0..instruction.param_count())
+                    .map(|n| (modes[n], self.get(self.ip + n + 1)))
+                    .collect(),
+                reads: Vec::new(),
+                writes: Vec::new(),
+            };
+
+            if let Some(tracer) = &mut self.tracer {
+                tracer.record = Some(record);
+            }
+        }
+
+        macro_rules! param {
+            ($n:expr) => {{
+                let mode = modes[$n];
+                let val = self.get(self.ip + $n + 1);
+
+                match mode {
+                    ParamMode::Immediate => val,
+                    ParamMode::Positional => self.read(self.address($n, val)?),
+                    ParamMode::Relative => self.read(self.address($n, self.relative(&val)?)?),
+                }
+            }};
+        }
+
+        macro_rules! param_dest {
+            ($n:expr, $val:expr) => {{
+                let mode = modes[$n];
+                let val = self.get(self.ip + $n + 1);
+
+                let dest = match mode {
+                    ParamMode::Positional => self.address($n, val)?,
+                    ParamMode::Relative => self.address($n, self.relative(&val)?)?,
+                    ParamMode::Immediate => {
+                        return Err(IntcodeError::ImmediateWrite {
+                            fault: self.fault(),
+                            param: $n,
+                        })
+                    }
+                };
+                self.write(dest, $val)?;
+            }};
+        }
+
+        macro_rules! binop {
+            ($op:expr) => {{
+                let left = param!(0);
+                let right = param!(1);
+                param_dest!(2, self.arith($op(&left, &right, self.arithmetic))?);
+            }};
+        }
+
+        match instruction {
+            OpCode::Add => binop!(W::add),
+            OpCode::Mul => binop!(W::mul),
+
+            OpCode::Input => {
+                if let Some(val) = input.take() {
+                    param_dest!(0, val);
+                } else {
+                    return Ok(Some(ExecutionStatus::AwaitingInput));
+                }
+            }
+
+            OpCode::Output => {
+                let val = param!(0);
+                self.ip += instruction.effect();
+                return Ok(Some(ExecutionStatus::Output(val)));
+            }
+
+            OpCode::TJmp => {
+                let pred = param!(0);
+                let dest = param!(1);
+
+                if !pred.is_zero() {
+                    self.ip = self.jump_target(dest)?;
+                    return Ok(None);
+                }
+            }
+
+            OpCode::FJmp => {
+                let pred = param!(0);
+                let dest = param!(1);
+
+                if pred.is_zero() {
+                    self.ip = self.jump_target(dest)?;
+                    return Ok(None);
+                }
+            }
+
+            OpCode::Lt => binop!(|a, b, _| Some(W::from_i64((a < b) as i64))),
+            OpCode::Eq => binop!(|a, b, _| Some(W::from_i64((a == b) as i64))),
+
+            OpCode::AdjustRelativeBase => {
+                let adjustment = param!(0);
+                self.relative_base =
+                    self.arith(self.relative_base.add(&adjustment, self.arithmetic))?;
+            }
+
+            OpCode::Halt => return Ok(Some(ExecutionStatus::Complete)),
+        }
+
+        self.ip += instruction.effect();
+
+        Ok(None)
+    }
+
+    /// Runs until the program produces output, needs input, halts, runs out
+    /// of fuel or hits a watchpoint.
+    ///
+    /// If the fuel runs out or a watchpoint is hit before `input` is read, it
+    /// is held until the next call, which must then be given `None`.
+    pub fn resume(&mut self, input: Option<W>) -> Result<ExecutionStatus<W>, IntcodeError<W>> {
+        if self.held_input.is_some() && input.is_some() {
+            return Err(IntcodeError::InputAlreadyHeld {
+                fault: self.fault(),
+            });
+        }
+        let mut input = self.held_input.take().or(input);
+
+        loop {
+            if self.backend == Backend::Compiled
+                && self.tracer.is_none()
+                && self.profiler.is_none()
+                && self.coverage.is_none()
+                && self.wx.is_none()
+                && self.watchpoints.is_empty()
+                && self.watch_hits.is_empty()
+                && self.fuel != Some(0)
+                && self.run_compiled()?
+            {
+                continue;
+            }
+
+            match self.step(&mut input)? {
+                Some(status @ ExecutionStatus::OutOfFuel)
+                | Some(status @ ExecutionStatus::Watchpoint(_)) => {
+                    self.held_input = input;
+                    return Ok(status);
+                }
+                Some(status) => return Ok(status),
+                None => {}
+            }
+        }
+    }
+
+    /// Runs the program to the end, taking its input from and giving its
+    /// output to `io`.
+    pub fn run_with<I: IntcodeIo<W>>(&mut self, io: &mut I) -> Result<(), IntcodeError<W>> {
+        let mut result = self.resume(None)?;
+
+        loop {
+            let fault = |vm: &VM<W>, e: std::io::Error| IntcodeError::IoFailed {
+                fault: vm.fault(),
+                message: e.to_string(),
+            };
+
+            result = match result {
+                ExecutionStatus::Complete => return Ok(()),
+                ExecutionStatus::AwaitingInput => match io.input().map_err(|e| fault(self, e))? {
+                    Some(n) => self.resume(Some(n))?,
+                    None => {
+                        return Err(IntcodeError::InputExhausted {
+                            fault: self.fault(),
+                        })
+                    }
+                },
+                ExecutionStatus::Output(n) => {
+                    io.output(n).map_err(|e| fault(self, e))?;
+                    self.resume(None)?
+                }
+                ExecutionStatus::OutOfFuel => {
+                    return Err(IntcodeError::OutOfFuel {
+                        fault: self.fault(),
+                    });
+                }
+                ExecutionStatus::Watchpoint(_) => self.resume(None)?,
+            };
+        }
+    }
+
+    pub fn run_to_completion<T>(&mut self, input: T) -> Result<Vec<W>, IntcodeError<W>>
+    where
+        T: Into<VecDeque<W>>,
+    {
+        let mut io = QueueIo::new(input);
+        self.run_with(&mut io)?;
+        Ok(io.output)
+    }
+}
+
+impl<W: Word> From<&Snapshot<W>> for VM<W> {
+    fn from(snapshot: &Snapshot<W>) -> Self {
+        let mut vm = VM::from_words(Vec::new());
+        vm.restore(snapshot);
+        vm
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rng::Rng;
+    use crate::word::BigInt;
+
+    #[test]
+    fn test_add_mul() {
+        let mut vm = VM::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+
+        assert_eq!(vm.run_to_completion(vec![]), Ok(vec![]));
+        assert_eq!(vm.get(0), 3500);
+    }
+
+    #[test]
+    fn test_comparisons() {
+        let code = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+
+        assert_eq!(
+            VM::new(code.clone()).run_to_completion(vec![8]),
+            Ok(vec![1])
+        );
+        assert_eq!(VM::new(code).run_to_completion(vec![7]), Ok(vec![0]));
+    }
+
+    #[test]
+    fn test_jumps() {
+        let code = vec![3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1];
+
+        assert_eq!(
+            VM::new(code.clone()).run_to_completion(vec![0]),
+            Ok(vec![0])
+        );
+        assert_eq!(VM::new(code).run_to_completion(vec![5]), Ok(vec![1]));
+    }
+
+    #[test]
+    fn test_quine() {
+        let code = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+
+        assert_eq!(VM::new(code.clone()).run_to_completion(vec![]), Ok(code));
+    }
+
+    #[test]
+    fn test_large_numbers() {
+        let mut vm = VM::new(vec![1102, 34_915_192, 34_915_192, 7, 4, 7, 99, 0]);
+        assert_eq!(
+            vm.run_to_completion(vec![]),
+            Ok(vec![1_219_070_632_396_864])
+        );
+
+        let mut vm = VM::new(vec![104, 1_125_899_906_842_624, 99]);
+        assert_eq!(
+            vm.run_to_completion(vec![]),
+            Ok(vec![1_125_899_906_842_624])
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let run = |arithmetic, code: Vec<i64>| {
+            let mut vm = VM::new(code);
+            vm.set_arithmetic(arithmetic);
+            vm.run_to_completion(vec![])
+        };
+
+        // The large number example from day 9 fits, whatever the mode
+        for arithmetic in &[
+            Arithmetic::Wrapping,
+            Arithmetic::Checked,
+            Arithmetic::Saturating,
+        ] {
+            assert_eq!(
+                run(
+                    *arithmetic,
+                    vec![1102, 34_915_192, 34_915_192, 7, 4, 7, 99, 0]
+                ),
+                Ok(vec![1_219_070_632_396_864])
+            );
+        }
+
+        // Squares it, which doesn't
+        let code = vec![
+            1102, 34_915_192, 34_915_192, 11, 2, 11, 11, 11, 4, 11, 99, 0,
+        ];
+        assert_eq!(
+            run(Arithmetic::Wrapping, code.clone()),
+            Ok(vec![
+                1_219_070_632_396_864_i64.wrapping_mul(1_219_070_632_396_864)
+            ])
+        );
+        assert_eq!(
+            run(Arithmetic::Saturating, code.clone()),
+            Ok(vec![i64::MAX])
+        );
+        assert_eq!(
+            run(Arithmetic::Checked, code),
+            Err(IntcodeError::Overflow {
+                fault: Fault {
+                    ip: 4,
+                    instruction: 2,
+                    relative_base: 0,
+                }
+            })
+        );
+
+        // Relative addresses and adjustments are checked too
+        assert!(matches!(
+            run(Arithmetic::Checked, vec![109, i64::MAX, 204, 1, 99]),
+            Err(IntcodeError::Overflow { fault }) if fault.ip == 2
+        ));
+        assert!(matches!(
+            run(Arithmetic::Checked, vec![109, i64::MAX, 109, 1, 99]),
+            Err(IntcodeError::Overflow { fault }) if fault.ip == 2
+        ));
+    }
+
+    #[test]
+    fn test_missing_input() {
+        let mut vm = VM::new(vec![3, 0, 99]);
+
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::AwaitingInput));
+        assert_eq!(
+            VM::new(vec![3, 0, 99]).run_to_completion(vec![]),
+            Err(IntcodeError::InputExhausted {
+                fault: Fault {
+                    ip: 0,
+                    instruction: 3,
+                    relative_base: 0,
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_param_mode() {
+        let mut vm = VM::new(vec![109, 5, 3101, 0, 0, 0, 99]);
+
+        assert_eq!(
+            vm.resume(None),
+            Err(IntcodeError::InvalidParamMode {
+                fault: Fault {
+                    ip: 2,
+                    instruction: 3101,
+                    relative_base: 5,
+                },
+                param: 1,
+                mode: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_immediate_write() {
+        let mut vm = VM::new(vec![11101, 1, 1, 0, 99]);
+
+        assert_eq!(
+            vm.resume(None),
+            Err(IntcodeError::ImmediateWrite {
+                fault: Fault {
+                    ip: 0,
+                    instruction: 11101,
+                    relative_base: 0,
+                },
+                param: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_negative_address() {
+        let mut vm = VM::new(vec![109, -10, 204, 3, 99]);
+
+        match vm.resume(None) {
+            Err(IntcodeError::NegativeAddress {
+                fault,
+                param,
+                address,
+            }) => {
+                assert_eq!(fault.ip, 2);
+                assert_eq!(param, 0);
+                assert_eq!(address, -7);
+            }
+            other => panic!("Unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_negative_jump() {
+        for backend in &[Backend::Interpreter, Backend::Compiled] {
+            let mut vm = VM::new(vec![1101, 0, -5, 8, 105, 1, 8, 99, 0]);
+            vm.set_backend(*backend);
+
+            let error = vm.resume(None).unwrap_err();
+            assert_eq!(
+                error,
+                IntcodeError::NegativeJump {
+                    fault: Fault {
+                        ip: 4,
+                        instruction: 105,
+                        relative_base: 0,
+                    },
+                    target: -5,
+                }
+            );
+            assert_eq!(
+                error.to_string(),
+                "Jump to negative address -5 at ip 4 (instruction 105, relative base 0)"
+            );
+        }
+
+        // Not taken, so it doesn't matter where it would have gone
+        let mut vm = VM::new(vec![1106, 1, -1, 99]);
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::Complete));
+    }
+
+    #[test]
+    fn test_unknown_opcode() {
+        for code in &[vec![0], vec![42], vec![-1], vec![1142], vec![i64::MIN]] {
+            let instruction = code[0];
+
+            assert_eq!(
+                VM::new(code.clone()).resume(None),
+                Err(IntcodeError::UnknownOpcode {
+                    fault: Fault {
+                        ip: 0,
+                        instruction,
+                        relative_base: 0,
+                    },
+                    opcode: instruction % 100,
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_data_as_code() {
+        // Jumps into the middle of the add instruction, where 1000 is not an
+        // opcode.
+        let mut vm = VM::new(vec![1105, 1, 4, 1101, 1000, 1, 7, 99]);
+
+        assert!(matches!(
+            vm.resume(None),
+            Err(IntcodeError::UnknownOpcode { fault, opcode: 0 }) if fault.ip == 4
+        ));
+    }
+
+    #[test]
+    fn test_garbage_programs() {
+        let mut rng = Rng::new(0);
+
+        for _ in 0..1000 {
+            let len = rng.range(1, 32) as usize;
+            let code = (0..len)
+                .map(|_| rng.range(-20_000, 19_999))
+                .collect::<Vec<_>>();
+            let mut vm = VM::new(code);
+            vm.set_fuel(Some(10_000));
+
+            loop {
+                match vm.resume(Some(0)) {
+                    Ok(ExecutionStatus::Complete) | Ok(ExecutionStatus::OutOfFuel) | Err(_) => {
+                        break
+                    }
+                    Ok(_) => {}
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_step() {
+        let mut vm = VM::new(vec![3, 9, 1001, 9, 5, 9, 4, 9, 99, 0]);
+        let mut input = None;
+
+        assert_eq!(
+            vm.step(&mut input),
+            Ok(Some(ExecutionStatus::AwaitingInput))
+        );
+        assert_eq!(vm.ip(), 0);
+
+        input = Some(10);
+        assert_eq!(vm.step(&mut input), Ok(None));
+        assert_eq!((input, vm.ip()), (None, 2));
+        assert_eq!(vm.step(&mut input), Ok(None));
+        assert_eq!(vm.get(9), 15);
+        assert_eq!(vm.step(&mut input), Ok(Some(ExecutionStatus::Output(15))));
+        assert_eq!(vm.step(&mut input), Ok(Some(ExecutionStatus::Complete)));
+        assert_eq!(vm.ip(), 8);
+    }
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trace() {
+        let buffer = SharedBuffer::default();
+        let mut vm = VM::new(vec![3, 9, 21001, 9, 2, 0, 109, 1, 99, 0]);
+        vm.trace_to(buffer.clone());
+
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::AwaitingInput));
+        assert_eq!(vm.resume(Some(7)), Ok(ExecutionStatus::Complete));
+        assert!(vm.stop_tracing().is_some());
+        vm.resume(None).unwrap();
+
+        let trace = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            trace.lines().collect::<Vec<_>>(),
+            vec![
+                concat!(
+                    r#"{"ip":0,"instruction":3,"opcode":"in","relative_base":0,"#,
+                    r#""operands":[{"mode":"positional","value":9}],"#,
+                    r#""reads":[],"writes":[{"address":9,"value":7}]}"#,
+                ),
+                concat!(
+                    r#"{"ip":2,"instruction":21001,"opcode":"add","relative_base":0,"#,
+                    r#""operands":[{"mode":"positional","value":9},"#,
+                    r#"{"mode":"immediate","value":2},{"mode":"relative","value":0}],"#,
+                    r#""reads":[{"address":9,"value":7}],"writes":[{"address":0,"value":9}]}"#,
+                ),
+                concat!(
+                    r#"{"ip":6,"instruction":109,"opcode":"arb","relative_base":0,"#,
+                    r#""operands":[{"mode":"immediate","value":1}],"reads":[],"writes":[]}"#,
+                ),
+                concat!(
+                    r#"{"ip":8,"instruction":99,"opcode":"hlt","relative_base":1,"#,
+                    r#""operands":[],"reads":[],"writes":[]}"#,
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_profile() {
+        // Counts to 3 and prints it
+        let mut vm = VM::new(vec![
+            1001, 14, 1, 14, 1007, 14, 3, 15, 1005, 15, 0, 4, 14, 99, 0, 0,
+        ]);
+        vm.set_backend(Backend::Compiled);
+        vm.start_profiling();
+
+        assert_eq!(vm.run_to_completion(vec![]), Ok(vec![3]));
+        assert_eq!(vm.profile().map(Profile::instructions), Some(11));
+
+        let profile = vm.stop_profiling().unwrap();
+        assert_eq!(
+            profile.by_address.into_iter().collect::<Vec<_>>(),
+            vec![(0, 3), (4, 3), (8, 3), (11, 1), (13, 1)]
+        );
+        assert_eq!(profile.by_opcode[&OpCode::Lt], 3);
+        assert_eq!(
+            profile
+                .spans
+                .iter()
+                .map(|span| (span.end, span.ip, span.instructions))
+                .collect::<Vec<_>>(),
+            vec![(OpCode::Output, 11, 10), (OpCode::Halt, 13, 1)]
+        );
+        assert!(vm.profile().is_none());
+    }
+
+    #[test]
+    fn test_coverage() {
+        // Prints the input if it's nonzero, or 0 otherwise
+        let code = vec![3, 12, 1005, 12, 8, 104, 0, 99, 4, 12, 99, 0, 0];
+        let mut vm = VM::new(code.clone());
+        vm.set_backend(Backend::Compiled);
+        vm.start_coverage();
+
+        assert_eq!(vm.run_to_completion(vec![5]), Ok(vec![5]));
+        assert_eq!(
+            vm.stop_coverage(),
+            Some(Coverage {
+                executed: vec![0, 2, 8, 10].into_iter().collect(),
+                read: vec![12].into_iter().collect(),
+                written: vec![12].into_iter().collect(),
+            })
+        );
+        assert!(vm.coverage().is_none());
+
+        let mut vm = VM::new(code);
+        vm.start_coverage();
+        assert_eq!(vm.run_to_completion(vec![0]), Ok(vec![0]));
+        assert_eq!(
+            vm.coverage().unwrap().executed,
+            vec![0, 2, 5, 7].into_iter().collect()
+        );
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let code = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut vm = VM::new(code.clone());
+
+        for _ in 0..5 {
+            vm.resume(None).unwrap();
+        }
+
+        let snapshot = vm.snapshot();
+        let rest = vm.run_to_completion(vec![]).unwrap();
+        assert_eq!(rest, code[5..]);
+
+        vm.restore(&snapshot);
+        assert_eq!(vm.run_to_completion(vec![]), Ok(rest.clone()));
+
+        let mut vm = VM::from(&snapshot.to_string().parse::<Snapshot>().unwrap());
+        assert_eq!(vm.run_to_completion(vec![]), Ok(rest));
+    }
+
+    #[test]
+    fn test_fuel() {
+        // Loops forever
+        let mut vm = VM::new(vec![1105, 1, 0]);
+        vm.set_fuel(Some(1000));
+
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::OutOfFuel));
+        assert_eq!(vm.fuel(), Some(0));
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::OutOfFuel));
+
+        let code = vec![1101, 1, 2, 11, 3, 12, 4, 11, 4, 12, 99, 0, 0];
+        let expected = VM::new(code.clone()).run_to_completion(vec![7]).unwrap();
+
+        for budget in 1..4 {
+            let mut vm = VM::new(code.clone());
+            let mut input = Some(7);
+            let mut output = Vec::new();
+            vm.set_fuel(Some(budget));
+
+            loop {
+                match vm.resume(input.take()).unwrap() {
+                    ExecutionStatus::OutOfFuel => vm.set_fuel(Some(budget)),
+                    ExecutionStatus::Output(n) => output.push(n),
+                    ExecutionStatus::Complete => break,
+                    ExecutionStatus::AwaitingInput => panic!("Input was lost"),
+                    ExecutionStatus::Watchpoint(_) => unreachable!(),
+                }
+            }
+
+            assert_eq!(output, expected);
+        }
+
+        let mut vm = VM::new(vec![1105, 1, 0]);
+        vm.set_fuel(Some(10));
+        assert!(matches!(
+            vm.run_to_completion(vec![]),
+            Err(IntcodeError::OutOfFuel { .. })
+        ));
+    }
+
+    #[test]
+    fn test_held_input() {
+        // Runs out of fuel before reading its input, then prints it
+        let mut vm = VM::new(vec![1101, 1, 1, 9, 3, 9, 4, 9, 99, 0]);
+        vm.set_fuel(Some(1));
+
+        assert_eq!(vm.resume(Some(7)), Ok(ExecutionStatus::OutOfFuel));
+        assert!(matches!(
+            vm.resume(Some(8)),
+            Err(IntcodeError::InputAlreadyHeld { fault }) if fault.ip == 4
+        ));
+
+        let snapshot = vm.snapshot();
+        assert_eq!(snapshot.held_input, Some(7));
+        vm.set_fuel(None);
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::Output(7)));
+
+        let mut vm = VM::from(&snapshot.to_string().parse::<Snapshot>().unwrap());
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::Output(7)));
+    }
+
+    #[test]
+    fn test_ran_off_end() {
+        let mut vm = VM::new(vec![1101, 1, 1, 5, 104, 0]);
+
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::Output(2)));
+        assert!(matches!(
+            vm.resume(None),
+            Err(IntcodeError::RanOffEnd { fault }) if fault.ip == 6
+        ));
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        // Reads a value into 100, doubles it in place and prints it
+        let mut vm = VM::new(vec![3, 100, 1002, 100, 2, 100, 4, 100, 99]);
+        vm.watch(100, Access::Write);
+        vm.watch(100, Access::Read);
+
+        let hit = |ip, access, old, new| WatchHit {
+            ip,
+            address: 100,
+            access,
+            old,
+            new,
+        };
+
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::AwaitingInput));
+        assert_eq!(
+            vm.resume(Some(21)),
+            Ok(ExecutionStatus::Watchpoint(vec![hit(
+                0,
+                Access::Write,
+                0,
+                21
+            )]))
+        );
+        assert_eq!(
+            vm.resume(None),
+            Ok(ExecutionStatus::Watchpoint(vec![
+                hit(2, Access::Read, 21, 21),
+                hit(2, Access::Write, 21, 42),
+            ]))
+        );
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::Output(42)));
+        assert_eq!(
+            vm.resume(None),
+            Ok(ExecutionStatus::Watchpoint(vec![hit(
+                6,
+                Access::Read,
+                42,
+                42
+            )]))
+        );
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::Complete));
+
+        // Input that hasn't been read yet is held across a watchpoint
+        let mut vm = VM::new(vec![1001, 9, 1, 9, 3, 10, 4, 10, 99, 5]);
+        vm.watch(9, Access::Write);
+        assert!(vm.unwatch(9, Access::Write));
+        assert!(!vm.unwatch(9, Access::Write));
+        vm.watch(9, Access::Read);
+
+        assert!(matches!(
+            vm.resume(Some(7)),
+            Ok(ExecutionStatus::Watchpoint(_))
+        ));
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::Output(7)));
+
+        let mut vm = VM::new(vec![1001, 9, 1, 9, 3, 10, 4, 10, 99, 5]);
+        vm.watch(9, Access::Read);
+        assert_eq!(vm.run_to_completion(vec![7]), Ok(vec![7]));
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let code = vec![1101, 1, 2, 4000, 4, 4000, 99];
+        let mut vm = VM::new(code.clone());
+        vm.set_memory_limit(Some(4000));
+
+        assert!(matches!(
+            vm.resume(None),
+            Err(IntcodeError::AddressOutOfRange {
+                param: 2,
+                address: 4000,
+                limit: 4000,
+                ..
+            })
+        ));
+
+        let mut vm = VM::new(code);
+        vm.set_memory_limit(Some(4001));
+        assert_eq!(vm.run_to_completion(vec![]), Ok(vec![3]));
+        assert_eq!(
+            vm.extra_memory().into_iter().collect::<Vec<_>>(),
+            vec![(4000, 3)]
+        );
+    }
+
+    #[test]
+    fn test_self_modifying_code() {
+        // Prints #7, then rewrites itself to print [7] instead and jumps back
+        let mut vm = VM::new(vec![104, 7, 1101, 4, 0, 0, 1106, 0, 0]);
+
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::Output(7)));
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::Output(0)));
+
+        // Makes it rewrite itself back to the original
+        vm.set(3, 104);
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::Output(7)));
+    }
+
+    #[test]
+    fn test_code_writes() {
+        // Prints #7, then rewrites itself to print [7] instead and jumps back
+        let code = vec![104, 7, 1101, 4, 0, 0, 1106, 0, 0];
+        let mut vm = VM::new(code.clone());
+        vm.set_backend(Backend::Compiled);
+        vm.start_code_write_detection(WxPolicy::Log);
+
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::Output(7)));
+        assert_eq!(vm.code_writes(), Some(&[][..]));
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::Output(0)));
+        vm.set(1, 8);
+        assert_eq!(
+            vm.stop_code_write_detection(),
+            Some(vec![CodeWrite {
+                ip: 2,
+                address: 0,
+                old: 104,
+                new: 4,
+            }])
+        );
+        assert!(vm.code_writes().is_none());
+
+        let mut vm = VM::new(code);
+        vm.start_code_write_detection(WxPolicy::Strict);
+
+        assert_eq!(vm.resume(None), Ok(ExecutionStatus::Output(7)));
+        assert_eq!(
+            vm.resume(None),
+            Err(IntcodeError::CodeWrite {
+                fault: Fault {
+                    ip: 2,
+                    instruction: 1101,
+                    relative_base: 0,
+                },
+                address: 0,
+            })
+        );
+        assert_eq!(vm.get(0), 104);
+
+        // Writing to data is fine
+        let mut vm = VM::new(vec![1101, 2, 3, 7, 4, 7, 99, 0]);
+        vm.start_code_write_detection(WxPolicy::Strict);
+        assert_eq!(vm.run_to_completion(vec![]), Ok(vec![5]));
+    }
+
+    #[test]
+    fn test_word_types() {
+        // Cubes the large number example from day 9, which only fits in a
+        // BigInt
+        let code = vec![
+            1102, 34_915_192, 34_915_192, 17, 2, 17, 17, 18, 4, 18, 2, 18, 17, 19, 4, 19, 99, 0, 0,
+            0,
+        ];
+        let square = "1486133206772489918753597034496";
+        let cube = "1811701348206118734441133628232592054970220544";
+
+        let mut vm = VM::<i128>::from_words(code.iter().map(|n| i128::from(*n)).collect());
+        vm.set_arithmetic(Arithmetic::Checked);
+        assert_eq!(
+            vm.resume(None),
+            Ok(ExecutionStatus::Output(square.parse().unwrap()))
+        );
+        assert!(matches!(
+            vm.resume(None),
+            Err(IntcodeError::Overflow { fault }) if fault.ip == 10
+        ));
+
+        for backend in &[Backend::Interpreter, Backend::Compiled] {
+            let mut vm = VM::from_words(code.iter().map(|n| BigInt::from(*n)).collect());
+            vm.set_backend(*backend);
+            assert_eq!(
+                vm.run_to_completion(vec![]),
+                Ok(vec![square.parse().unwrap(), cube.parse().unwrap()])
+            );
+        }
+
+        // Words too large to be addresses or opcodes
+        let mut vm = VM::<i128>::from_words(vec![4, 1 << 64, 99]);
+        assert!(matches!(
+            vm.resume(None),
+            Err(IntcodeError::AddressOutOfRange { address, limit: usize::MAX, .. })
+                if address == 1 << 64
+        ));
+        let mut vm = VM::<i128>::from_words(vec![(1 << 64) + 42]);
+        assert!(matches!(
+            vm.resume(None),
+            Err(IntcodeError::UnknownOpcode { opcode: 58, .. })
+        ));
+
+        let day9 = crate::parse_program(include_str!("../../day9/input.txt")).unwrap();
+        let mut vm = VM::from_words(day9.iter().map(|n| BigInt::from(*n)).collect());
+        assert_eq!(
+            vm.run_to_completion(vec![BigInt::from(1)]),
+            Ok(VM::new(day9)
+                .run_to_completion(vec![1])
+                .unwrap()
+                .into_iter()
+                .map(BigInt::from)
+                .collect())
+        );
+    }
+
+    /// Everything observable about running `code` to the end on `backend`.
+    fn run_on(
+        backend: Backend,
+        arithmetic: Arithmetic,
+        code: &[i64],
+    ) -> (Vec<ExecutionStatus>, String) {
+        let mut vm = VM::new(code.to_vec());
+        vm.set_backend(backend);
+        vm.set_arithmetic(arithmetic);
+        vm.set_fuel(Some(2000));
+
+        let mut statuses = Vec::new();
+        let end = loop {
+            match vm.resume(Some(1)) {
+                Ok(ExecutionStatus::Complete) => break "complete".to_string(),
+                Ok(ExecutionStatus::OutOfFuel) => break "out of fuel".to_string(),
+                Ok(status) => statuses.push(status),
+                Err(e) => break e.to_string(),
+            }
+        };
+
+        (
+            statuses,
+            format!("{}\n{}\n{:?}", end, vm.snapshot(), vm.fuel()),
+        )
+    }
+
+    #[test]
+    fn test_backends() {
+        let day9 = crate::parse_program(include_str!("../../day9/input.txt")).unwrap();
+
+        for input in 1..=2 {
+            let mut vm = VM::new(day9.clone());
+            vm.set_backend(Backend::Compiled);
+            assert_eq!(
+                vm.run_to_completion(vec![input]),
+                VM::new(day9.clone()).run_to_completion(vec![input])
+            );
+        }
+
+        // Programs made of mostly valid instructions with small operands, so
+        // they loop and modify themselves
+        let mut rng = Rng::new(0);
+
+        for _ in 0..1000 {
+            let len = rng.range(4, 43) as usize;
+            let mut code = Vec::new();
+
+            while code.len() < len {
+                let opcode = rng.choose(&OpCode::ALL);
+                let modes = (0..opcode.param_count())
+                    .fold(0, |acc, n| acc + rng.range(0, 2) * 10_i64.pow(n as u32));
+                code.push(modes * 100 + opcode as i64);
+                for _ in 0..opcode.param_count() {
+                    code.push(rng.range(-2, len as i64 + 1));
+                }
+            }
+
+            for arithmetic in &[Arithmetic::Wrapping, Arithmetic::Checked] {
+                assert_eq!(
+                    run_on(Backend::Compiled, *arithmetic, &code),
+                    run_on(Backend::Interpreter, *arithmetic, &code),
+                    "{:?}",
+                    code
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,296 @@
+//! A backend that compiles runs of instructions into chains of closures.
+//!
+//! A block starts wherever execution finds itself and runs up to and
+//! including the next jump, stopping early before anything that has to go
+//! through the interpreter: input, output, `Halt`, words that don't decode,
+//! and words the program has modified since they were first compiled. Each
+//! instruction in a block becomes a closure with its operands already
+//! decoded, so running a block doesn't look at the instruction words at all.
+//!
+//! Writing to any word of a compiled block throws away every block and marks
+//! the word as modified, after which it is always interpreted.
+
+use std::fmt;
+use std::sync::Arc;
+
+use super::VM;
+use crate::arithmetic::Arithmetic;
+use crate::error::IntcodeError;
+use crate::opcode::{Instruction, OpCode, ParamMode};
+use crate::word::Word;
+
+/// The way a VM executes instructions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// Decodes and executes one instruction at a time.
+    Interpreter,
+    /// Compiles runs of instructions into closures, interpreting anything it
+    /// can't compile. Tracing, profiling, coverage, code write detection and
+    /// watchpoints always use the interpreter.
+    Compiled,
+}
+
+impl Default for Backend {
+    /// `Compiled` when the `compiled` feature is enabled, otherwise
+    /// `Interpreter`.
+    fn default() -> Self {
+        if cfg!(feature = "compiled") {
+            Backend::Compiled
+        } else {
+            Backend::Interpreter
+        }
+    }
+}
+
+/// An operand with its mode already decoded.
+#[derive(Debug, Clone)]
+enum Load<W> {
+    Immediate(W),
+    Positional(W),
+    Relative(W),
+}
+
+impl<W: Word> Load<W> {
+    fn new(mode: ParamMode, value: W) -> Self {
+        match mode {
+            ParamMode::Immediate => Load::Immediate(value),
+            ParamMode::Positional => Load::Positional(value),
+            ParamMode::Relative => Load::Relative(value),
+        }
+    }
+
+    fn address(&self, vm: &VM<W>, param: usize) -> Result<usize, IntcodeError<W>> {
+        match self {
+            Load::Positional(address) => vm.address(param, address.clone()),
+            Load::Relative(offset) => vm.address(param, vm.relative(offset)?),
+            Load::Immediate(_) => unreachable!("immediate destinations aren't compiled"),
+        }
+    }
+
+    fn get(&self, vm: &VM<W>, param: usize) -> Result<W, IntcodeError<W>> {
+        match self {
+            Load::Immediate(value) => Ok(value.clone()),
+            _ => Ok(vm.get(self.address(vm, param)?)),
+        }
+    }
+}
+
+/// Executes an instruction, returning the destination of a jump if it takes
+/// one.
+type Run<W> = Box<dyn Fn(&mut VM<W>) -> Result<Option<usize>, IntcodeError<W>> + Send + Sync>;
+
+struct Op<W> {
+    ip: usize,
+    next: usize,
+    run: Run<W>,
+}
+
+pub(super) struct Block<W> {
+    ops: Vec<Op<W>>,
+}
+
+impl<W> fmt::Debug for Block<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Block")
+            .field("start", &self.ops[0].ip)
+            .field("end", &self.ops[self.ops.len() - 1].next)
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+enum Slot<W> {
+    /// Nothing has been compiled starting here yet.
+    Unknown,
+    /// The instruction here has to be interpreted.
+    Interpret,
+    Block(Arc<Block<W>>),
+}
+
+impl<W> Clone for Slot<W> {
+    fn clone(&self) -> Self {
+        match self {
+            Slot::Unknown => Slot::Unknown,
+            Slot::Interpret => Slot::Interpret,
+            Slot::Block(block) => Slot::Block(Arc::clone(block)),
+        }
+    }
+}
+
+/// The compiled blocks of a program.
+#[derive(Debug)]
+pub(super) struct Compiled<W> {
+    /// The block starting at each address of the program.
+    slots: Vec<Slot<W>>,
+    /// Whether each word is part of a compiled block.
+    covered: Vec<bool>,
+    /// Whether the program has written to each word after compiling it.
+    modified: Vec<bool>,
+    /// Set when a write throws the blocks away, so the block being run
+    /// can stop.
+    invalidated: bool,
+}
+
+impl<W> Compiled<W> {
+    pub fn new(len: usize) -> Self {
+        Self {
+            slots: vec![Slot::Unknown; len],
+            covered: vec![false; len],
+            modified: vec![false; len],
+            invalidated: false,
+        }
+    }
+
+    /// Notes a write to `idx`, which is inside the program.
+    pub fn write(&mut self, idx: usize) {
+        if self.covered[idx] {
+            self.modified[idx] = true;
+            self.invalidated = true;
+            self.slots.iter_mut().for_each(|slot| *slot = Slot::Unknown);
+            self.covered.iter_mut().for_each(|covered| *covered = false);
+        }
+    }
+}
+
+fn binop<W: Word>(
+    a: Load<W>,
+    b: Load<W>,
+    c: Load<W>,
+    op: fn(&W, &W, Arithmetic) -> Option<W>,
+) -> Run<W> {
+    Box::new(move |vm| {
+        let left = a.get(vm, 0)?;
+        let right = b.get(vm, 1)?;
+        let dest = c.address(vm, 2)?;
+        let val = vm.arith(op(&left, &right, vm.arithmetic))?;
+        vm.set(dest, val);
+        Ok(None)
+    })
+}
+
+fn jump<W: Word>(pred: Load<W>, dest: Load<W>, when: bool) -> Run<W> {
+    Box::new(move |vm| {
+        let taken = pred.get(vm, 0)?.is_zero() != when;
+        let dest = dest.get(vm, 1)?;
+        Ok(if taken {
+            Some(vm.jump_target(dest)?)
+        } else {
+            None
+        })
+    })
+}
+
+/// Compiles the block starting at `start`, if there is at least one
+/// instruction there that can be compiled.
+fn compile<W: Word>(vm: &VM<W>, start: usize) -> Option<Block<W>> {
+    let mut ops = Vec::new();
+    let mut ip = start;
+
+    while ip < vm.len {
+        let Instruction { opcode, modes } = match vm.get(ip).to_i64().map(Instruction::decode) {
+            Some(Ok(instruction)) => instruction,
+            _ => break,
+        };
+        let next = ip + opcode.effect();
+
+        if next > vm.len || vm.compiled.modified[ip..next].contains(&true) {
+            break;
+        }
+        if opcode
+            .dest_param()
+            .is_some_and(|n| modes[n] == ParamMode::Immediate)
+        {
+            break;
+        }
+
+        let load = |n: usize| Load::new(modes[n], vm.get(ip + n + 1));
+        let run: Run<W> = match opcode {
+            OpCode::Add => binop(load(0), load(1), load(2), W::add),
+            OpCode::Mul => binop(load(0), load(1), load(2), W::mul),
+            OpCode::Lt => binop(load(0), load(1), load(2), |a, b, _| {
+                Some(W::from_i64((a < b) as i64))
+            }),
+            OpCode::Eq => binop(load(0), load(1), load(2), |a, b, _| {
+                Some(W::from_i64((a == b) as i64))
+            }),
+            OpCode::TJmp => jump(load(0), load(1), true),
+            OpCode::FJmp => jump(load(0), load(1), false),
+            OpCode::AdjustRelativeBase => {
+                let adjustment = load(0);
+                Box::new(move |vm| {
+                    let adjustment = adjustment.get(vm, 0)?;
+                    vm.relative_base =
+                        vm.arith(vm.relative_base.add(&adjustment, vm.arithmetic))?;
+                    Ok(None)
+                })
+            }
+            OpCode::Input | OpCode::Output | OpCode::Halt => break,
+        };
+
+        ops.push(Op { ip, next, run });
+        ip = next;
+
+        if opcode == OpCode::TJmp || opcode == OpCode::FJmp {
+            break;
+        }
+    }
+
+    if ops.is_empty() {
+        None
+    } else {
+        Some(Block { ops })
+    }
+}
+
+impl<W: Word> VM<W> {
+    /// Runs the compiled block at `ip`, compiling it first if need be.
+    /// Returns `false` without doing anything if the instruction at `ip` has
+    /// to be interpreted, or if there isn't enough fuel for the whole block.
+    pub(super) fn run_compiled(&mut self) -> Result<bool, IntcodeError<W>> {
+        if self.ip >= self.len {
+            return Ok(false);
+        }
+
+        let block = match &self.compiled.slots[self.ip] {
+            Slot::Block(block) => Arc::clone(block),
+            Slot::Interpret => return Ok(false),
+            Slot::Unknown => match compile(self, self.ip) {
+                Some(block) => {
+                    let block = Arc::new(block);
+                    let end = block.ops[block.ops.len() - 1].next;
+                    self.compiled.covered[self.ip..end]
+                        .iter_mut()
+                        .for_each(|covered| *covered = true);
+                    self.compiled.slots[self.ip] = Slot::Block(Arc::clone(&block));
+                    block
+                }
+                None => {
+                    self.compiled.slots[self.ip] = Slot::Interpret;
+                    return Ok(false);
+                }
+            },
+        };
+
+        if self.fuel.is_some_and(|fuel| fuel < block.ops.len() as u64) {
+            return Ok(false);
+        }
+
+        self.compiled.invalidated = false;
+
+        for op in &block.ops {
+            self.ip = op.ip;
+            let dest = (op.run)(self)?;
+            self.ip = dest.unwrap_or(op.next);
+
+            if let Some(fuel) = &mut self.fuel {
+                *fuel -= 1;
+            }
+            if self.compiled.invalidated {
+                self.compiled.invalidated = false;
+                break;
+            }
+        }
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,47 @@
+use std::fmt;
+
+use crate::word::Word;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+/// Pauses execution whenever an instruction accesses `address` in the given
+/// way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Watchpoint {
+    pub address: usize,
+    pub access: Access,
+}
+
+/// An access that triggered a watchpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchHit<W = i64> {
+    /// The address of the instruction that made the access.
+    pub ip: usize,
+    pub address: usize,
+    pub access: Access,
+    /// The value at `address` before the access.
+    pub old: W,
+    /// The value at `address` after the access, the same as `old` for reads.
+    pub new: W,
+}
+
+impl<W: Word> fmt::Display for WatchHit<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.access {
+            Access::Read => write!(
+                f,
+                "read {} from {:04} at ip {:04}",
+                self.old, self.address, self.ip
+            ),
+            Access::Write => write!(
+                f,
+                "wrote {:04}: {} -> {} at ip {:04}",
+                self.address, self.old, self.new, self.ip
+            ),
+        }
+    }
+}
@@ -0,0 +1,394 @@
+//! The types a VM can use for the words of its memory.
+
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::hash::Hash;
+use std::str::FromStr;
+
+use crate::arithmetic::Arithmetic;
+
+pub trait Word:
+    Clone + Default + fmt::Debug + fmt::Display + FromStr + Ord + Hash + Send + Sync + 'static
+{
+    fn from_i64(n: i64) -> Self;
+
+    /// The word as an `i64`, if it fits in one.
+    fn to_i64(&self) -> Option<i64>;
+
+    /// Adds `other`, returning `None` on overflow in `Checked` mode.
+    fn add(&self, other: &Self, arithmetic: Arithmetic) -> Option<Self>;
+
+    /// Multiplies by `other`, returning `None` on overflow in `Checked` mode.
+    fn mul(&self, other: &Self, arithmetic: Arithmetic) -> Option<Self>;
+
+    fn is_zero(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn is_negative(&self) -> bool {
+        *self < Self::default()
+    }
+}
+
+impl Word for i64 {
+    fn from_i64(n: i64) -> Self {
+        n
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        Some(*self)
+    }
+
+    #[inline]
+    fn add(&self, other: &Self, arithmetic: Arithmetic) -> Option<Self> {
+        arithmetic.add(*self, *other)
+    }
+
+    #[inline]
+    fn mul(&self, other: &Self, arithmetic: Arithmetic) -> Option<Self> {
+        arithmetic.mul(*self, *other)
+    }
+}
+
+impl Word for i128 {
+    fn from_i64(n: i64) -> Self {
+        i128::from(n)
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        if *self >= i128::from(i64::MIN) && *self <= i128::from(i64::MAX) {
+            Some(*self as i64)
+        } else {
+            None
+        }
+    }
+
+    fn add(&self, other: &Self, arithmetic: Arithmetic) -> Option<Self> {
+        match arithmetic {
+            Arithmetic::Wrapping => Some(self.wrapping_add(*other)),
+            Arithmetic::Checked => self.checked_add(*other),
+            Arithmetic::Saturating => Some(self.saturating_add(*other)),
+        }
+    }
+
+    fn mul(&self, other: &Self, arithmetic: Arithmetic) -> Option<Self> {
+        match arithmetic {
+            Arithmetic::Wrapping => Some(self.wrapping_mul(*other)),
+            Arithmetic::Checked => self.checked_mul(*other),
+            Arithmetic::Saturating => Some(self.saturating_mul(*other)),
+        }
+    }
+}
+
+/// An integer of any size, which never overflows whatever the `Arithmetic`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    /// Base 2^32 digits, least significant first, with no trailing zeros so
+    /// that each number has one representation. Zero has none.
+    magnitude: Vec<u32>,
+}
+
+fn trim(magnitude: &mut Vec<u32>) {
+    while magnitude.last() == Some(&0) {
+        magnitude.pop();
+    }
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+
+    for i in 0..a.len().max(b.len()) {
+        let sum = u64::from(*a.get(i).unwrap_or(&0)) + u64::from(*b.get(i).unwrap_or(&0)) + carry;
+        result.push(sum as u32);
+        carry = sum >> 32;
+    }
+    result.push(carry as u32);
+
+    trim(&mut result);
+    result
+}
+
+/// `a - b`, where `a` is at least `b`.
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+
+    for (i, digit) in a.iter().enumerate() {
+        let diff = i64::from(*digit) - i64::from(*b.get(i).unwrap_or(&0)) - borrow;
+        result.push(diff.rem_euclid(1 << 32) as u32);
+        borrow = (diff < 0) as i64;
+    }
+
+    trim(&mut result);
+    result
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut result = vec![0; a.len() + b.len()];
+
+    for (i, x) in a.iter().enumerate() {
+        let mut carry = 0;
+        for (j, y) in b.iter().enumerate() {
+            let product = u64::from(*x) * u64::from(*y) + u64::from(result[i + j]) + carry;
+            result[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        result[i + b.len()] = carry as u32;
+    }
+
+    trim(&mut result);
+    result
+}
+
+impl BigInt {
+    fn new(negative: bool, mut magnitude: Vec<u32>) -> Self {
+        trim(&mut magnitude);
+        Self {
+            negative: negative && !magnitude.is_empty(),
+            magnitude,
+        }
+    }
+
+    /// Divides the magnitude by a small number in place, returning the
+    /// remainder.
+    fn div_rem_small(magnitude: &mut Vec<u32>, divisor: u32) -> u32 {
+        let mut rem = 0;
+
+        for digit in magnitude.iter_mut().rev() {
+            let value = (rem << 32) | u64::from(*digit);
+            *digit = (value / u64::from(divisor)) as u32;
+            rem = value % u64::from(divisor);
+        }
+
+        trim(magnitude);
+        rem as u32
+    }
+}
+
+impl From<i64> for BigInt {
+    fn from(n: i64) -> Self {
+        let magnitude = n.unsigned_abs();
+        BigInt::new(n < 0, vec![magnitude as u32, (magnitude >> 32) as u32])
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.magnitude, &other.magnitude),
+            (true, true) => cmp_magnitude(&other.magnitude, &self.magnitude),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.magnitude.is_empty() {
+            return write!(f, "0");
+        }
+
+        // Nine decimal digits at a time, least significant first
+        let mut chunks = Vec::new();
+        let mut magnitude = self.magnitude.clone();
+        while !magnitude.is_empty() {
+            chunks.push(BigInt::div_rem_small(&mut magnitude, 1_000_000_000));
+        }
+
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", chunks.pop().unwrap())?;
+        for chunk in chunks.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseBigIntError;
+
+impl fmt::Display for ParseBigIntError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid digit found in string")
+    }
+}
+
+impl Error for ParseBigIntError {}
+
+impl FromStr for BigInt {
+    type Err = ParseBigIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (negative, digits) = match s.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseBigIntError);
+        }
+
+        let mut magnitude = Vec::new();
+        for digit in digits.bytes() {
+            magnitude = add_magnitude(
+                &mul_magnitude(&magnitude, &[10]),
+                &[u32::from(digit - b'0')],
+            );
+        }
+
+        Ok(BigInt::new(negative, magnitude))
+    }
+}
+
+impl Word for BigInt {
+    fn from_i64(n: i64) -> Self {
+        BigInt::from(n)
+    }
+
+    fn to_i64(&self) -> Option<i64> {
+        if self.magnitude.len() > 2 {
+            return None;
+        }
+
+        let magnitude = self
+            .magnitude
+            .iter()
+            .rev()
+            .fold(0_u64, |acc, digit| (acc << 32) | u64::from(*digit));
+
+        if self.negative {
+            0_i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        }
+    }
+
+    fn add(&self, other: &Self, _: Arithmetic) -> Option<Self> {
+        if self.negative == other.negative {
+            return Some(BigInt::new(
+                self.negative,
+                add_magnitude(&self.magnitude, &other.magnitude),
+            ));
+        }
+
+        Some(match cmp_magnitude(&self.magnitude, &other.magnitude) {
+            Ordering::Less => BigInt::new(
+                other.negative,
+                sub_magnitude(&other.magnitude, &self.magnitude),
+            ),
+            _ => BigInt::new(
+                self.negative,
+                sub_magnitude(&self.magnitude, &other.magnitude),
+            ),
+        })
+    }
+
+    fn mul(&self, other: &Self, _: Arithmetic) -> Option<Self> {
+        Some(BigInt::new(
+            self.negative != other.negative,
+            mul_magnitude(&self.magnitude, &other.magnitude),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn big(s: &str) -> BigInt {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for s in &[
+            "0",
+            "1",
+            "-1",
+            "4294967296",
+            "-9223372036854775808",
+            "123456789012345678901234567890",
+            "-1000000000000000000000000000000000001",
+        ] {
+            assert_eq!(big(s).to_string(), *s);
+        }
+
+        assert_eq!(big("-0"), BigInt::default());
+        assert_eq!(big("+007").to_string(), "7");
+        assert_eq!("".parse::<BigInt>(), Err(ParseBigIntError));
+        assert_eq!("1x".parse::<BigInt>(), Err(ParseBigIntError));
+    }
+
+    #[test]
+    fn test_i64() {
+        for n in &[0, 1, -1, 1 << 32, -(1 << 40), i64::MAX, i64::MIN] {
+            let word = BigInt::from_i64(*n);
+            assert_eq!(word.to_string(), n.to_string());
+            assert_eq!(word.to_i64(), Some(*n));
+        }
+
+        assert_eq!(big("9223372036854775808").to_i64(), None);
+        assert_eq!(big("-9223372036854775809").to_i64(), None);
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let add = |a: &str, b: &str| big(a).add(&big(b), Arithmetic::Checked).unwrap();
+        let mul = |a: &str, b: &str| big(a).mul(&big(b), Arithmetic::Checked).unwrap();
+
+        assert_eq!(add("9223372036854775807", "1"), big("9223372036854775808"));
+        assert_eq!(add("-5", "3"), big("-2"));
+        assert_eq!(add("5", "-5"), BigInt::default());
+        assert_eq!(add("-4294967296", "1"), big("-4294967295"));
+        assert_eq!(
+            mul("1219070632396864", "1219070632396864"),
+            big("1486133206772489918753597034496")
+        );
+        assert_eq!(mul("-3", "4"), big("-12"));
+        assert_eq!(mul("-3", "0"), BigInt::default());
+    }
+
+    #[test]
+    fn test_ordering() {
+        let mut words = [
+            big("5"),
+            big("-10000000000000"),
+            big("0"),
+            big("-1"),
+            big("99999999999999"),
+        ];
+        words.sort();
+        assert_eq!(
+            words.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            vec!["-10000000000000", "-1", "0", "5", "99999999999999"]
+        );
+    }
+
+    #[test]
+    fn test_i128() {
+        assert_eq!(i128::from_i64(-3).to_i64(), Some(-3));
+        assert_eq!((i128::from(i64::MAX) + 1).to_i64(), None);
+        assert_eq!(i128::MAX.add(&1, Arithmetic::Checked), None);
+        assert_eq!(i128::MAX.add(&1, Arithmetic::Saturating), Some(i128::MAX));
+    }
+}
@@ -0,0 +1,98 @@
+//! Detecting programs that write over their own instructions.
+
+use std::fmt;
+
+use crate::word::Word;
+
+/// What to do when a program writes to a word of an instruction it has
+/// already executed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WxPolicy {
+    /// Record the write and carry on.
+    Log,
+    /// Fail with `IntcodeError::CodeWrite` before the write is made, so that
+    /// memory is never both executed and written.
+    Strict,
+}
+
+/// A write to a word of an instruction that had already been executed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeWrite<W = i64> {
+    /// The address of the instruction that made the write.
+    pub ip: usize,
+    pub address: usize,
+    pub old: W,
+    pub new: W,
+}
+
+impl<W: Word> fmt::Display for CodeWrite<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ip {:04} wrote {:04}: {} -> {}",
+            self.ip, self.address, self.old, self.new
+        )
+    }
+}
+
+/// Keeps track of the executed words and the writes made to them.
+#[derive(Debug)]
+pub(crate) struct WxGuard<W> {
+    pub policy: WxPolicy,
+    pub writes: Vec<CodeWrite<W>>,
+    /// Whether each address has been executed, as an opcode or a parameter.
+    executed: Vec<bool>,
+}
+
+impl<W> WxGuard<W> {
+    pub fn new(policy: WxPolicy) -> Self {
+        Self {
+            policy,
+            writes: Vec::new(),
+            executed: Vec::new(),
+        }
+    }
+
+    /// Marks the `len` words of the instruction at `ip` as executed.
+    pub fn execute(&mut self, ip: usize, len: usize) {
+        if self.executed.len() < ip + len {
+            self.executed.resize(ip + len, false);
+        }
+        for executed in &mut self.executed[ip..ip + len] {
+            *executed = true;
+        }
+    }
+
+    pub fn executed(&self, address: usize) -> bool {
+        self.executed.get(address).copied().unwrap_or(false)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_executed() {
+        let mut guard = WxGuard::<i64>::new(WxPolicy::Log);
+        guard.execute(4, 3);
+        guard.execute(0, 2);
+
+        assert_eq!(
+            (0..8)
+                .filter(|address| guard.executed(*address))
+                .collect::<Vec<_>>(),
+            vec![0, 1, 4, 5, 6]
+        );
+        assert_eq!(
+            CodeWrite {
+                ip: 2,
+                address: 5,
+                old: 104_i64,
+                new: 4,
+            }
+            .to_string(),
+            "ip 0002 wrote 0005: 104 -> 4"
+        );
+    }
+}